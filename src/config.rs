@@ -0,0 +1,42 @@
+//! Defaults for the command line, read from `config.yaml` under
+//! `~/.config/ifnotnow`, or from the file `INN_CONFIG` names. Flags
+//! given on the command line take precedence.
+use super::*;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Config {
+   /// The budget of new timeboxes, in seconds.
+   pub default_budget_s: Option<u64>,
+   /// The directory of the timelines, when `--dir` is not given.
+   pub dir: Option<String>,
+   /// The command that edits a timeline when `$EDITOR` is not set, with
+   /// any arguments it needs, like `code --wait`.
+   pub editor: Option<String>,
+}
+impl Config {
+   fn filename() -> Option<PathBuf> {
+      if let Ok(path) = std::env::var("INN_CONFIG") {
+         return Some(PathBuf::from(path));
+      }
+      let home = std::env::var("HOME").ok()?;
+      Some(Path::new(&home).join(".config/ifnotnow/config.yaml"))
+   }
+   /// A missing file is the same as an empty one.
+   pub fn load() -> Result<Config, INNError> {
+      let filename = match Config::filename() {
+         Some(filename) => filename,
+         None => return Ok(Config::default()),
+      };
+      match std::fs::read_to_string(&filename) {
+         Ok(text) if text.trim().is_empty() => Ok(Config::default()),
+         Ok(text) => serde_yaml::from_str(&text)
+            .map_err(|e| INNError::BadConfig(filename.display().to_string(), e)),
+         Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+         Err(e) => Err(INNError::File(e)),
+      }
+   }
+   /// The budget for a timebox added without `--budget`.
+   pub fn default_budget(&self) -> Timespan {
+      Timespan::new(self.default_budget_s.unwrap_or(3600))
+   }
+}
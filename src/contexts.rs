@@ -1,9 +1,13 @@
 use super::*;
-use larch::LarchMinimal;
+use larch::{EffectStep, LarchEffects, LarchMinimal};
 
+/// The commands on timelines and the view history. This is the one
+/// command enum: the top-level `Cmd` wraps it as `Cmd::Context`, and
+/// `ViewCmd` is only what `ViewHistory::apply` takes, converted to and
+/// from the view variants here.
 #[derive(Debug, Eq, Ord, PartialEq, PartialOrd, Clone)]
 pub enum Cmd {
-    Init(String),
+    Init(String, Option<String>),
     List,
     Search(String, Query),
     Switch(String),
@@ -13,26 +17,284 @@ pub enum Cmd {
     Load(String),
     Save(String),
     Mark(String, Event),
+    Up,
+    Down,
+    Toggle(String, usize),
+    Start(String, usize),
+    Pause(String, usize),
 }
 
-struct ContextMod;
-struct ContextFlags;
-struct ContextModel;
+impl Cmd {
+    /// The change to the view history, for `Switch`, `Last`, `Next` and
+    /// `Clear`.
+    pub fn as_view(&self) -> Option<ViewCmd> {
+        match self {
+            Cmd::Switch(name) => Some(ViewCmd::Switch(name.to_string())),
+            Cmd::Last => Some(ViewCmd::Last),
+            Cmd::Next => Some(ViewCmd::Next),
+            Cmd::Clear => Some(ViewCmd::Clear),
+            _ => None,
+        }
+    }
+}
+impl From<ViewCmd> for Cmd {
+    fn from(cmd: ViewCmd) -> Cmd {
+        match cmd {
+            ViewCmd::Switch(name) => Cmd::Switch(name),
+            ViewCmd::Last => Cmd::Last,
+            ViewCmd::Next => Cmd::Next,
+            ViewCmd::Clear => Cmd::Clear,
+        }
+    }
+}
+impl From<Cmd> for super::Cmd {
+    fn from(cxc: Cmd) -> super::Cmd {
+        super::Cmd::Context(cxc)
+    }
+}
+
+/// Executes a context command against the model. View history changes
+/// are saved right away; loaded timelines stay in the model until a
+/// `Save`.
+pub fn run(cxc: &Cmd, model: &mut Model) -> Result<(), INNError> {
+    match cxc {
+        Cmd::Init(name, template) => init_timeline(name, template.as_deref()),
+        Cmd::List => {
+            for name in context_names()?.iter() {
+                println!("{}", name);
+            }
+            Ok(())
+        }
+        Cmd::Search(name, query) => {
+            for (name, count) in search_contexts(&[name.to_string()], query)?.iter() {
+                println!("{}: {}", name, count);
+            }
+            Ok(())
+        }
+        Cmd::Switch(_) | Cmd::Last | Cmd::Next | Cmd::Clear => view(cxc.as_view().unwrap(), model),
+        Cmd::Load(name) => {
+            model.contexts.insert(ListV1::load(name)?);
+            Ok(())
+        }
+        Cmd::Save(name) => match model.contexts.get(name) {
+            Some(list) => list.save(),
+            None => Err(INNError::NotFound(ListV1::filename(name))),
+        },
+        Cmd::Mark(name, event) => {
+            let mut timeline = ListV1::load(name)?;
+            timeline.items.push(ListItem::Timebox(event.to_timebox()));
+            timeline.save()
+        }
+        Cmd::Up | Cmd::Down => Err(INNError::TuiOnly(String::from("moving the cursor"))),
+        Cmd::Toggle(name, idx) | Cmd::Start(name, idx) | Cmd::Pause(name, idx) => {
+            let mut timeline = ListV1::load(name)?;
+            if let Some(item) = timeline.items.get_mut(*idx) {
+                edit(cxc, item);
+            }
+            timeline.save()
+        }
+    }
+}
+
+/// Applies `Toggle`, `Start` or `Pause` to an item. Goals are toggled
+/// and timeboxes started or paused; anything else is left alone.
+fn edit(cxc: &Cmd, item: &mut ListItem) {
+    match (cxc, item) {
+        (Cmd::Toggle(_, _), ListItem::Goal(goal)) => goal.set_done(!goal.done, Utc::now()),
+        (Cmd::Start(_, _), ListItem::Timebox(tb)) if !tb.is_running() => {
+            tb.history.push(AttentionEvent::Started(Utc::now()));
+            tb.accrued = tb.recompute_accrued();
+        }
+        (Cmd::Pause(_, _), ListItem::Timebox(tb)) if tb.is_running() => {
+            tb.history.push(AttentionEvent::Paused(Utc::now()));
+            tb.accrued = tb.recompute_accrued();
+        }
+        _ => {}
+    }
+}
+
+fn view(cmd: ViewCmd, model: &mut Model) -> Result<(), INNError> {
+    model.view_history.apply(&cmd);
+    model.save()
+}
+
+pub struct ContextMod;
+/// What the contexts application starts from, usually read from the
+/// persisted `Model`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextFlags {
+    pub now_context: Option<String>,
+    pub view_history: ViewHistory,
+}
+/// The loaded timelines, which one is in view and the index of the item
+/// under the cursor. The links in view are resolved as it is loaded or
+/// switched to, not for every frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextModel {
+    pub contexts: ListMap,
+    pub now_context: Option<String>,
+    pub view_history: ViewHistory,
+    pub cursor: usize,
+    pub links: LinkTargets,
+}
+impl ContextModel {
+    fn resolve_links(&mut self) {
+        self.links = self
+            .now_context
+            .as_ref()
+            .and_then(|name| self.contexts.get(name))
+            .map(LinkTargets::resolve)
+            .unwrap_or_default();
+    }
+    fn follow_view(mut self) -> (ContextModel, Option<Cmd>) {
+        self.now_context = self.view_history.current().map(|name| name.to_string());
+        self.cursor = 0;
+        self.resolve_links();
+        let msg = match &self.now_context {
+            Some(name) if !self.contexts.contains(name) => {
+                Some(Cmd::Load(name.to_string()))
+            }
+            _ => None,
+        };
+        (self, msg)
+    }
+}
 impl LarchMinimal for ContextMod {
     type Flags = contexts::ContextFlags;
     type Model = contexts::ContextModel;
     type Msg = contexts::Cmd;
     type View = tui::View;
+    /// Starts with nothing loaded and asks for the timeline for now.
     fn init(flags: Self::Flags) -> (Self::Model, Option<Self::Msg>) {
-        (ContextModel {}, None)
+        let msg = flags
+            .now_context
+            .as_ref()
+            .map(|name| Cmd::Load(name.to_string()));
+        let model = ContextModel {
+            contexts: ListMap::new(),
+            now_context: flags.now_context,
+            view_history: flags.view_history,
+            cursor: 0,
+            links: LinkTargets::default(),
+        };
+        (model, msg)
     }
+    /// `Init` and `Mark` follow up with a `Save` of the timeline. Edits
+    /// at the cursor are kept in the model until a `Save`.
     fn update(
         cxc: Self::Msg,
-        model: Self::Model,
+        mut model: Self::Model,
     ) -> Result<(Self::Model, Option<Self::Msg>), anyhow::Error> {
-        Ok((model, None))
+        match cxc {
+            Cmd::Init(name, template) => {
+                model.contexts.add(&name)?;
+                if let Some(template) = template {
+                    model.contexts.insert(template::timeline(&template, &name)?);
+                }
+                Ok((model, Some(Cmd::Save(name))))
+            }
+            Cmd::List | Cmd::Search(_, _) => Ok((model, None)),
+            Cmd::Switch(_) | Cmd::Last | Cmd::Next | Cmd::Clear => {
+                model.view_history.apply(&cxc.as_view().unwrap());
+                Ok(model.follow_view())
+            }
+            Cmd::Load(name) => {
+                model.contexts.insert(ListV1::load(&name)?);
+                model.resolve_links();
+                Ok((model, None))
+            }
+            Cmd::Save(name) => match model.contexts.get(&name) {
+                Some(list) => {
+                    list.save()?;
+                    Ok((model, None))
+                }
+                None => Err(INNError::NotFound(ListV1::filename(&name)).into()),
+            },
+            Cmd::Mark(name, event) => {
+                if !model.contexts.contains(&name) {
+                    model.contexts.insert(ListV1::load(&name)?);
+                }
+                let list = model.contexts.get_mut(&name).unwrap();
+                list.items.push(ListItem::Timebox(event.to_timebox()));
+                Ok((model, Some(Cmd::Save(name))))
+            }
+            Cmd::Up => {
+                model.cursor = model.cursor.saturating_sub(1);
+                Ok((model, None))
+            }
+            Cmd::Down => {
+                let len = model
+                    .now_context
+                    .as_ref()
+                    .and_then(|name| model.contexts.get(name))
+                    .map_or(0, |list| list.items.len());
+                if model.cursor + 1 < len {
+                    model.cursor += 1;
+                }
+                Ok((model, None))
+            }
+            Cmd::Toggle(ref name, idx) | Cmd::Start(ref name, idx) | Cmd::Pause(ref name, idx) => {
+                if let Some(item) = model
+                    .contexts
+                    .get_mut(name)
+                    .and_then(|list| list.items.get_mut(idx))
+                {
+                    edit(&cxc, item);
+                }
+                Ok((model, None))
+            }
+        }
+    }
+    /// Shows the timeline for now, or nothing when none is loaded. The
+    /// cursor goes on the first line of the item under it.
+    fn view(model: &Self::Model) -> (Self::View, Option<Self::Msg>) {
+        let mut view = tui::View::default();
+        let list = model
+            .now_context
+            .as_ref()
+            .and_then(|name| model.contexts.get(name));
+        for (i, item) in list.iter().flat_map(|list| list.items.iter()).enumerate() {
+            if i == model.cursor {
+                view.cursor = view.lines.len();
+            }
+            let rendered = render_item(item, "", Utc::now(), &model.links);
+            view.lines.extend(rendered.lines().map(|line| line.to_string()));
+        }
+        (view, None)
+    }
+}
+
+/// The IO the contexts application asks for.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Effect {
+    Save(ListV1),
+}
+/// Saving is an effect, so `update_with_effects` writes nothing itself.
+/// Loading still happens inline in `update`.
+impl LarchEffects for ContextMod {
+    type Effect = contexts::Effect;
+    fn update_with_effects(
+        cxc: Self::Msg,
+        model: Self::Model,
+    ) -> Result<EffectStep<Self>, anyhow::Error> {
+        match cxc {
+            Cmd::Save(name) => match model.contexts.get(&name) {
+                Some(list) => {
+                    let effect = Effect::Save(list.clone());
+                    Ok((model, None, vec![effect]))
+                }
+                None => Err(INNError::NotFound(ListV1::filename(&name)).into()),
+            },
+            cxc => {
+                let (model, msg) = Self::update(cxc, model)?;
+                Ok((model, msg, vec![]))
+            }
+        }
     }
-    fn view(model: Self::Model) -> (Self::View, Option<Self::Msg>) {
-        (Self::View {}, None)
+    fn perform(effect: Self::Effect) -> Result<Option<Self::Msg>, anyhow::Error> {
+        match effect {
+            Effect::Save(list) => list.save()?,
+        }
+        Ok(None)
     }
 }
@@ -0,0 +1,66 @@
+//! With `--dry-run` nothing is written. Saves report on stderr what they
+//! would have changed instead, one line per item added or removed.
+use super::*;
+
+/// Whether writes are skipped, set once from `--dry-run`.
+pub static DRY_RUN: OnceLock<bool> = OnceLock::new();
+
+pub fn dry_run() -> bool {
+   DRY_RUN.get().copied().unwrap_or(false)
+}
+
+/// The kind and label of an item, marked when done.
+fn describe(item: &ListItem) -> String {
+   let kind = match item {
+      ListItem::Heading(_) => "Heading",
+      ListItem::Entry(_) => "Entry",
+      ListItem::Note(_) => "Note",
+      ListItem::Goal(_) => "Goal",
+      ListItem::Timebox(_) => "Timebox",
+      ListItem::Sublist(_) => "Sublist",
+      ListItem::Place(_) => "Place",
+      ListItem::Link(_) => "Link",
+   };
+   match item.is_done() {
+      Some(true) => format!("{}: {} (done)", kind, item.label()),
+      _ => format!("{}: {}", kind, item.label()),
+   }
+}
+
+/// The items of `old` missing from `new`, by position from 1, followed
+/// by the items of `new` that were not in `old`. An edited item shows
+/// up as removed and added again.
+pub fn changes(old: &[ListItem], new: &[ListItem]) -> Vec<String> {
+   let mut unmatched: Vec<&ListItem> = new.iter().collect();
+   let mut out = vec![];
+   for (idx, item) in old.iter().enumerate() {
+      match unmatched.iter().position(|other| *other == item) {
+         Some(pos) => {
+            unmatched.remove(pos);
+         }
+         None => out.push(format!("- removed item {}: {}", idx + 1, describe(item))),
+      }
+   }
+   out.extend(unmatched.iter().map(|item| format!("+ {}", describe(item))));
+   out
+}
+
+/// Tells what saving `new` over the timeline on disk would change.
+pub fn report(new: &ListV1) -> Result<(), INNError> {
+   let old = match ListV1::load(&new.name) {
+      Ok(old) => old.items,
+      Err(INNError::NotFound(filename)) => {
+         eprintln!("+ new timeline {}", filename);
+         vec![]
+      }
+      Err(e) => return Err(e),
+   };
+   let lines = changes(&old, &new.items);
+   if lines.is_empty() && !old.is_empty() {
+      eprintln!("no changes to {}", ListV1::filename(&new.name));
+   }
+   for line in lines {
+      eprintln!("{}", line);
+   }
+   Ok(())
+}
@@ -0,0 +1,75 @@
+//! The filters `inn now` narrows a timeline with. Each axis flag adds a
+//! `Filter`, and `--any` or `--all` says how they combine.
+use super::*;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+   Tag(String),
+   Person(String),
+   Project(String),
+   OverBudget,
+   /// Activity at or after the first time and at or before the second.
+   Active(Option<DTUtc>, Option<DTUtc>),
+}
+impl Filter {
+   pub fn matches(&self, item: &ListItem) -> bool {
+      match self {
+         Filter::Tag(tag) => item.tags().iter().any(|t| t.as_str() == tag),
+         Filter::Person(person) => item.people().iter().any(|p| p.as_str() == person),
+         Filter::Project(project) => item.projects().iter().any(|p| p.as_str() == project),
+         Filter::OverBudget => match item {
+            ListItem::Timebox(tb) => tb.overage().is_some(),
+            _ => false,
+         },
+         Filter::Active(since, until) => {
+            let in_range =
+               |ts: &DTUtc| since.is_none_or(|s| *ts >= s) && until.is_none_or(|u| *ts <= u);
+            // Timeboxes count any event in range, other items their
+            // timestamp, and items without one are left out.
+            match item {
+               ListItem::Timebox(tb) => tb.history.iter().any(|event| in_range(&event.at())),
+               _ => item.timestamp().is_some_and(|ts| in_range(&ts)),
+            }
+         }
+      }
+   }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Combine {
+   And,
+   Or,
+}
+
+/// Whether the item passes all of the filters, or any of them.
+pub fn matches_item(item: &ListItem, filters: &[Filter], combine: Combine) -> bool {
+   match combine {
+      Combine::And => filters.iter().all(|filter| filter.matches(item)),
+      Combine::Or => filters.iter().any(|filter| filter.matches(item)),
+   }
+}
+
+/// The filters asked for by the flags of `now`. Tags, people and
+/// projects may be given more than once.
+pub fn from_args(matches: &ArgMatches) -> Result<Vec<Filter>, INNError> {
+   let mut filters = vec![];
+   let values = |name: &str, marker: char| -> Vec<String> {
+      matches
+         .values_of(name)
+         .into_iter()
+         .flatten()
+         .map(|v| v.trim_start_matches(marker).to_lowercase())
+         .collect()
+   };
+   filters.extend(values("tag", '#').into_iter().map(Filter::Tag));
+   filters.extend(values("person", '@').into_iter().map(Filter::Person));
+   filters.extend(values("project", '+').into_iter().map(Filter::Project));
+   if matches.is_present("over-budget") {
+      filters.push(Filter::OverBudget);
+   }
+   let (since, until) = (time_arg(matches, "since")?, time_arg(matches, "until")?);
+   if since.is_some() || until.is_some() {
+      filters.push(Filter::Active(since, until));
+   }
+   Ok(filters)
+}
@@ -0,0 +1,77 @@
+//! Geoviews locate places on a projection.
+use super::*;
+use std::cmp::Ordering;
+
+/// A labelled place, in degrees.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GeoPoint {
+   pub lat: f64,
+   pub lon: f64,
+   pub label: String,
+}
+// Items are ordered and compared as a whole, so places need a total
+// order even though their coordinates are floats.
+impl Ord for GeoPoint {
+   fn cmp(&self, other: &Self) -> Ordering {
+      self
+         .lat
+         .total_cmp(&other.lat)
+         .then(self.lon.total_cmp(&other.lon))
+         .then_with(|| self.label.cmp(&other.label))
+   }
+}
+impl PartialOrd for GeoPoint {
+   fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+      Some(self.cmp(other))
+   }
+}
+impl PartialEq for GeoPoint {
+   fn eq(&self, other: &Self) -> bool {
+      self.cmp(other) == Ordering::Equal
+   }
+}
+impl Eq for GeoPoint {}
+
+/// Maps a place onto a plane as `(x, y)`.
+pub trait Projection {
+   fn project(&self, p: &GeoPoint) -> (f64, f64);
+}
+
+/// Longitude scaled by the cosine of the standard parallel, and
+/// latitude, both in degrees. The default parallel is the equator.
+#[derive(Debug, Default)]
+pub struct Equirectangular {
+   pub standard_parallel: f64,
+}
+impl Projection for Equirectangular {
+   fn project(&self, p: &GeoPoint) -> (f64, f64) {
+      (p.lon * self.standard_parallel.to_radians().cos(), p.lat)
+   }
+}
+
+/// Lists the places in the timeline and its sublists from north to
+/// south with their coordinates and where they land on the projection.
+pub fn render_geo_view(list: &ListV1, projection: &dyn Projection) -> String {
+   fn collect<'a>(list: &'a ListV1, places: &mut Vec<&'a GeoPoint>) {
+      for item in list.items.iter() {
+         match item {
+            ListItem::Place(p) => places.push(p),
+            ListItem::Sublist(sub) => collect(sub, places),
+            _ => {}
+         }
+      }
+   }
+   let mut places = vec![];
+   collect(list, &mut places);
+   places.sort_by(|a, b| b.cmp(a));
+   let mut out = String::from("");
+   for p in places.iter() {
+      let (x, y) = projection.project(p);
+      out.push_str(&format!(
+         " - {} ({:.4}, {:.4}) at ({:.2}, {:.2})\n",
+         p.label, p.lat, p.lon, x, y
+      ));
+   }
+   out
+}
@@ -0,0 +1,66 @@
+//! How timelines refer to each other through their `Link` items, for
+//! `inn graph`.
+use super::*;
+
+#[derive(Debug, Serialize)]
+pub struct Edge {
+   pub from: String,
+   pub to: String,
+   #[serde(skip_serializing_if = "Option::is_none")]
+   pub item: Option<usize>,
+   pub label: String,
+   pub broken: bool,
+}
+
+/// Timelines are the nodes and links the edges. Targets that do not
+/// exist only show up as the end of a broken edge.
+#[derive(Debug, Default, Serialize)]
+pub struct Graph {
+   pub nodes: Vec<String>,
+   pub edges: Vec<Edge>,
+}
+impl Graph {
+   pub fn add(&mut self, list: &ListV1) {
+      fn collect(from: &str, list: &ListV1, targets: &LinkTargets, edges: &mut Vec<Edge>) {
+         for item in list.items.iter() {
+            match item {
+               ListItem::Link(link) => edges.push(Edge {
+                  from: from.to_string(),
+                  to: link.target.to_string(),
+                  item: link.item,
+                  label: link.label.to_string(),
+                  broken: link.is_broken(targets),
+               }),
+               ListItem::Sublist(sub) => collect(from, sub, targets, edges),
+               _ => {}
+            }
+         }
+      }
+      self.nodes.push(list.name.to_string());
+      collect(&list.name, list, &LinkTargets::resolve(list), &mut self.edges);
+   }
+   /// A Graphviz digraph with the broken links dashed.
+   pub fn render_dot(&self) -> String {
+      let mut out = String::from("digraph ifnotnow {\n");
+      for node in self.nodes.iter() {
+         out.push_str(&format!("   {};\n", quote(node)));
+      }
+      for edge in self.edges.iter() {
+         let style = if edge.broken { ", style=dashed" } else { "" };
+         out.push_str(&format!(
+            "   {} -> {} [label={}{}];\n",
+            quote(&edge.from),
+            quote(&edge.to),
+            quote(&edge.label),
+            style
+         ));
+      }
+      out.push_str("}\n");
+      out
+   }
+}
+
+/// A DOT string literal.
+fn quote(s: &str) -> String {
+   format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
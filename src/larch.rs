@@ -10,13 +10,104 @@ pub trait LarchMinimal {
     /// How the model is translated into a view
     type View;
 
-    fn run(flags: Self::Flags) {
-        let (mut model, mut msg) = Self::init(flags);
+    /// Starts the application and applies messages until none is left.
+    fn run(flags: Self::Flags) -> Result<Self::Model, anyhow::Error>
+    where
+        Self: Sized,
+    {
+        run_to_fixpoint::<Self>(flags, None)
     }
     fn init(flags: Self::Flags) -> (Self::Model, Option<Self::Msg>);
     fn update(
         msg: Self::Msg,
         model: Self::Model,
     ) -> Result<(Self::Model, Option<Self::Msg>), anyhow::Error>;
-    fn view(model: Self::Model) -> (Self::View, Option<Self::Msg>);
+    fn view(model: &Self::Model) -> (Self::View, Option<Self::Msg>);
+}
+
+/// The most messages one `settle` applies before giving up, in case
+/// updates keep asking for each other.
+pub const MAX_STEPS: usize = 1000;
+
+/// Applies the message and every follow-up `update` returns, until an
+/// update returns none.
+pub fn settle<A: LarchMinimal>(
+    mut model: A::Model,
+    mut msg: Option<A::Msg>,
+) -> Result<A::Model, anyhow::Error> {
+    let mut steps = 0;
+    while let Some(m) = msg {
+        if steps == MAX_STEPS {
+            anyhow::bail!("updates did not settle after {} messages", MAX_STEPS);
+        }
+        steps += 1;
+        let (next_model, next_msg) = A::update(m, model)?;
+        model = next_model;
+        msg = next_msg;
+    }
+    Ok(model)
+}
+
+/// Runs `init`, settles the message it returns and then settles
+/// `initial_msg`, returning the model they leave.
+pub fn run_to_fixpoint<A: LarchMinimal>(
+    flags: A::Flags,
+    initial_msg: Option<A::Msg>,
+) -> Result<A::Model, anyhow::Error> {
+    let (model, msg) = A::init(flags);
+    let model = settle::<A>(model, msg)?;
+    settle::<A>(model, initial_msg)
+}
+
+/// The model, follow-up message and effects an update returns.
+pub type EffectStep<A> = (
+    <A as LarchMinimal>::Model,
+    Option<<A as LarchMinimal>::Msg>,
+    Vec<<A as LarchEffects>::Effect>,
+);
+
+/// A Larch application whose updates can ask for IO, like writing a
+/// file, instead of doing it inline. `perform` does the IO, so the rest
+/// of `update_with_effects` stays a pure model transition.
+pub trait LarchEffects: LarchMinimal {
+    type Effect;
+
+    /// `update`, plus the effects to perform afterwards. By default
+    /// there are none.
+    fn update_with_effects(
+        msg: Self::Msg,
+        model: Self::Model,
+    ) -> Result<EffectStep<Self>, anyhow::Error> {
+        let (model, msg) = Self::update(msg, model)?;
+        Ok((model, msg, vec![]))
+    }
+    /// Performs an effect, perhaps with a message to update with next.
+    /// By default it does nothing.
+    fn perform(_effect: Self::Effect) -> Result<Option<Self::Msg>, anyhow::Error> {
+        Ok(None)
+    }
+}
+
+/// `settle` for applications with effects. Each update's effects are
+/// performed before its follow-up, and the messages they produce are
+/// applied after it, in order.
+pub fn settle_with_effects<A: LarchEffects>(
+    mut model: A::Model,
+    msg: Option<A::Msg>,
+) -> Result<A::Model, anyhow::Error> {
+    let mut queue: std::collections::VecDeque<A::Msg> = msg.into_iter().collect();
+    let mut steps = 0;
+    while let Some(m) = queue.pop_front() {
+        if steps == MAX_STEPS {
+            anyhow::bail!("updates did not settle after {} messages", MAX_STEPS);
+        }
+        steps += 1;
+        let (next_model, next_msg, effects) = A::update_with_effects(m, model)?;
+        model = next_model;
+        queue.extend(next_msg);
+        for effect in effects.into_iter() {
+            queue.extend(A::perform(effect)?);
+        }
+    }
+    Ok(model)
 }
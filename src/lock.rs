@@ -0,0 +1,49 @@
+//! Advisory locks that keep two `inn` processes from reading and
+//! writing a timeline at the same time. A lock is a sentinel file next
+//! to the one it guards, created exclusively and removed when dropped.
+//!
+//! Whoever needs both takes the timeline's lock before the undo
+//! journal's, as a save does when it journals the file, so two `inn`s
+//! never each hold the lock the other is waiting for.
+use super::*;
+use std::time::{Duration, Instant};
+
+/// How long to keep trying before giving up with `INNError::Locked`.
+const TIMEOUT: Duration = Duration::from_secs(3);
+const RETRY: Duration = Duration::from_millis(50);
+
+pub struct Lock {
+   filename: String,
+}
+impl Lock {
+   /// Waits for the lock on `filename`, held as `filename.lock`.
+   pub fn acquire(filename: &str) -> Result<Lock, INNError> {
+      let filename = format!("{}.lock", filename);
+      let start = Instant::now();
+      loop {
+         match std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&filename)
+         {
+            Ok(_) => return Ok(Lock { filename }),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+               if start.elapsed() >= TIMEOUT {
+                  return Err(INNError::Locked(filename));
+               }
+               std::thread::sleep(RETRY);
+            }
+            // A directory that does not exist yet has nothing to guard.
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+               return Ok(Lock { filename })
+            }
+            Err(e) => return Err(INNError::File(e)),
+         }
+      }
+   }
+}
+impl Drop for Lock {
+   fn drop(&mut self) {
+      let _ = std::fs::remove_file(&self.filename);
+   }
+}
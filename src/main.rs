@@ -3,24 +3,81 @@
 //! - local first software guidelines
 //! - use todo.txt
 //!
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::fmt;
 use std::fs::File;
 use std::io::prelude::*;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
-use anyhow;
 use chrono::prelude::*;
-use clap::{App, Arg, ArgMatches, SubCommand};
-use regex;
+use clap::{App, Arg, ArgGroup, ArgMatches, Shell, SubCommand};
+use serde::{Deserialize, Serialize};
 
+#[macro_use]
+mod verbosity;
 mod todotxt;
 mod contexts;
 mod larch;
+mod migrate;
+mod geoview;
+mod undo;
+mod config;
+mod markdown;
+mod recurrence;
 mod tui;
+mod dryrun;
+mod filter;
+mod graph;
+mod watch;
+mod lock;
+mod template;
+mod report;
 
 pub type DTUtc = DateTime<Utc>;
 
-const IFNOTNOW_EXTENSION: &str = ".txt";
+const IFNOTNOW_EXTENSION: &str = ".inn.yaml";
+/// Bookkeeping for the whole directory is kept next to the timelines.
+const IFNOTNOW_MODEL: &str = "model";
+/// Names whose files hold bookkeeping rather than a timeline.
+const RESERVED_NAMES: &[&str] = &[IFNOTNOW_MODEL, undo::IFNOTNOW_UNDO];
+
+/// Refuses a name for a new timeline when its file would be one of the
+/// bookkeeping files, which a timeline written over would break.
+fn check_name(name: &str) -> Result<(), INNError> {
+   if RESERVED_NAMES.contains(&name) {
+      return Err(INNError::ReservedName(name.to_string()));
+   }
+   Ok(())
+}
+
+/// Where the timelines live, set once from `--dir` or `INN_DIR`.
+static STORE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Every path to a timeline is built from here; the current directory
+/// unless told otherwise.
+fn store_dir() -> &'static Path {
+   STORE_DIR
+      .get()
+      .map(|dir| dir.as_path())
+      .unwrap_or_else(|| Path::new(""))
+}
+
+/// Whether timelines are shown in color, set once from `--color`.
+static COLOR: OnceLock<bool> = OnceLock::new();
+
+fn color() -> bool {
+   COLOR.get().copied().unwrap_or(false)
+}
+
+/// The columns notes and entries wrap at, set once by `now`. Unset, or
+/// not on a terminal without `--width`, they do not wrap.
+static WIDTH: OnceLock<Option<usize>> = OnceLock::new();
+
+fn width() -> Option<usize> {
+   WIDTH.get().copied().flatten()
+}
 
 /// Patterns for searching contexts
 #[derive(Eq, Clone, PartialEq, PartialOrd, Ord, Debug)]
@@ -35,29 +92,56 @@ impl Pattern {
          Keyword(_) => None,
          Regex(rx) => match regex::Regex::new(rx) {
             Ok(_) => None,
-            _ => Some(PatternErr::InvalidRegex),
+            Err(e) => Some(PatternErr::InvalidRegex(e.to_string())),
          },
       }
    }
+   /// Counts the hits of the pattern in a piece of text. Keywords are
+   /// case-insensitive substrings, regexes count non-overlapping matches.
+   fn count_in(&self, text: &str) -> Result<usize, PatternErr> {
+      Ok(self.spans_in(text)?.len())
+   }
+   /// The byte ranges of the hits counted by `count_in`.
+   fn spans_in(&self, text: &str) -> Result<Vec<(usize, usize)>, PatternErr> {
+      use Pattern::*;
+      let re = match self {
+         Keyword(kw) if kw.is_empty() => return Ok(vec![]),
+         Keyword(kw) => regex::Regex::new(&format!("(?i){}", regex::escape(kw))),
+         Regex(rx) => regex::Regex::new(rx),
+      };
+      match re {
+         Ok(re) => Ok(re.find_iter(text).map(|m| (m.start(), m.end())).collect()),
+         Err(e) => Err(PatternErr::InvalidRegex(e.to_string())),
+      }
+   }
 }
 
-/// Some patterns may have syntax errors.
+/// Some patterns may have syntax errors. The message is the one from
+/// the regex crate, which points at the problem.
+#[derive(Debug)]
 pub enum PatternErr {
-   InvalidRegex,
+   InvalidRegex(String),
 }
 
 /// Trait for how anything could be matched against a pattern.
 pub trait Matchable {
    /// Returns the number of matches of pattern in traited data.
-   fn matches(&self, pattern: Pattern) -> Result<usize, PatternErr> {
+   fn matches(&self, _pattern: Pattern) -> Result<usize, PatternErr> {
       Ok(0)
    }
+   /// Returns the byte ranges of the matches in the traited data's own
+   /// text, for anything that has one.
+   fn match_spans(&self, _pattern: &Pattern) -> Result<Vec<(usize, usize)>, PatternErr> {
+      Ok(vec![])
+   }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
 pub enum Query {
    ContextNames(Pattern),
    ContextItems(Pattern),
+   /// Only the goals and timeboxes that are done, or open when false.
+   ContextItemsDone(Pattern, bool),
 }
 
 #[derive(Debug, Eq, Ord, PartialEq, PartialOrd)]
@@ -68,22 +152,93 @@ pub enum ViewCmd {
    Clear,
 }
 
+/// What `run` queues for a subcommand. Commands on timelines and the
+/// view history are all `contexts::Cmd`; the rest name their handler in
+/// `HANDLERS`. A change to the view history is followed by a `Show` of
+/// the timeline it lands on.
 #[derive(Debug, Eq, Ord, PartialEq, PartialOrd)]
-/// Commands trigger fun things
 pub enum Cmd {
    Noop,
    Help,
    Context(contexts::Cmd),
-   View(ViewCmd),
+   Handler(&'static str),
+   Show(String),
+}
+
+/// Contexts visited, most recent last, with a cursor for moving back
+/// and forth like a browser history.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ViewHistory {
+   visited: Vec<String>,
+   cursor: usize,
+}
+impl ViewHistory {
+   fn current(&self) -> Option<&str> {
+      self.visited.get(self.cursor).map(|name| name.as_str())
+   }
+   fn apply(&mut self, cmd: &ViewCmd) {
+      match cmd {
+         ViewCmd::Switch(name) => {
+            self.visited.truncate(self.cursor + 1);
+            self.visited.push(name.to_string());
+            self.cursor = self.visited.len() - 1;
+         }
+         ViewCmd::Last => self.cursor = self.cursor.saturating_sub(1),
+         ViewCmd::Next => {
+            if self.cursor + 1 < self.visited.len() {
+               self.cursor += 1;
+            }
+         }
+         ViewCmd::Clear => {
+            self.visited.clear();
+            self.cursor = 0;
+         }
+      }
+   }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Model {
    now_context: Option<String>,
+   #[serde(skip, default = "ListMap::new")]
    contexts: ListMap,
+   #[serde(default)]
+   view_history: ViewHistory,
+}
+impl Model {
+   fn new() -> Model {
+      Model {
+         now_context: None,
+         contexts: ListMap::new(),
+         view_history: ViewHistory::default(),
+      }
+   }
+   fn filename() -> String {
+      ListV1::filename(IFNOTNOW_MODEL)
+   }
+   /// Loads the model, starting afresh when there is none yet.
+   fn load() -> Result<Model, INNError> {
+      match std::fs::File::open(Model::filename()) {
+         Ok(file) => {
+            let reader = std::io::BufReader::new(file);
+            match serde_yaml::from_reader(reader) {
+               Ok(m) => Ok(m),
+               Err(e) => Err(INNError::Yaml(e)),
+            }
+         }
+         Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Model::new()),
+         Err(e) => Err(INNError::File(e)),
+      }
+   }
+   fn save(&self) -> Result<(), INNError> {
+      let model_yaml = serde_yaml::to_string(self)?;
+      write_atomically(&Model::filename(), &model_yaml)
+   }
 }
 
-#[derive(Eq, Clone, Debug, PartialEq, PartialOrd, Deserialize, Serialize, Ord)]
+/// Written as a human duration like `1h30m`, see `from_human`.
+#[derive(Eq, Clone, Debug, PartialEq, PartialOrd, Ord, Default)]
 pub struct Timespan {
    duration_s: u64,
 }
@@ -91,13 +246,110 @@ impl Timespan {
    pub fn new(duration_s: u64) -> Timespan {
       Timespan { duration_s }
    }
+   /// Parses durations like `90m`, `1h30m`, `2h`, `45s` or `1d`. A bare
+   /// number is taken as seconds.
+   pub fn from_human(s: &str) -> Result<Timespan, INNError> {
+      let bad = || INNError::BadDuration(s.to_string());
+      let s = s.trim();
+      if let Ok(duration_s) = s.parse::<u64>() {
+         return Ok(Timespan::new(duration_s));
+      }
+      let mut duration_s: u64 = 0;
+      let mut digits = String::new();
+      for c in s.chars() {
+         if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+         }
+         let unit_s = match c {
+            'd' => 86400,
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(bad()),
+         };
+         let n: u64 = digits.parse().map_err(|_| bad())?;
+         duration_s = n
+            .checked_mul(unit_s)
+            .and_then(|d| d.checked_add(duration_s))
+            .ok_or_else(bad)?;
+         digits.clear();
+      }
+      if s.is_empty() || !digits.is_empty() {
+         return Err(bad());
+      }
+      Ok(Timespan::new(duration_s))
+   }
+   pub fn as_secs(&self) -> u64 {
+      self.duration_s
+   }
+   /// Both spans together, capped at the longest span there is.
+   pub fn add(&self, other: &Timespan) -> Timespan {
+      Timespan::new(self.duration_s.saturating_add(other.duration_s))
+   }
+   /// What is left after taking `other` away, or nothing.
+   pub fn saturating_sub(&self, other: &Timespan) -> Timespan {
+      Timespan::new(self.duration_s.saturating_sub(other.duration_s))
+   }
+   /// How much of the budget is left when this much has been spent.
+   pub fn remaining(&self, budget: &Timespan) -> Timespan {
+      budget.saturating_sub(self)
+   }
+}
+impl std::ops::Add for Timespan {
+   type Output = Timespan;
+   fn add(self, other: Timespan) -> Timespan {
+      Timespan::add(&self, &other)
+   }
 }
+impl std::ops::AddAssign for Timespan {
+   fn add_assign(&mut self, other: Timespan) {
+      *self = Timespan::add(self, &other);
+   }
+}
+/// Shows `1h30m` rather than `5400s` once there is more than a minute.
 impl fmt::Display for Timespan {
    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-      write!(f, "{}s", self.duration_s)
+      if self.duration_s < 60 {
+         return write!(f, "{}s", self.duration_s);
+      }
+      let units = [("d", 86400), ("h", 3600), ("m", 60), ("s", 1)];
+      let mut rest = self.duration_s;
+      for (unit, unit_s) in units.iter() {
+         if rest >= *unit_s {
+            write!(f, "{}{}", rest / unit_s, unit)?;
+            rest %= unit_s;
+         }
+      }
+      Ok(())
+   }
+}
+
+impl Serialize for Timespan {
+   fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+      serializer.serialize_str(&self.to_string())
+   }
+}
+/// Reads `1h30m`, a bare number of seconds, or the `duration_s:` map
+/// that older files have.
+impl<'de> Deserialize<'de> for Timespan {
+   fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Timespan, D::Error> {
+      #[derive(Deserialize)]
+      #[serde(untagged)]
+      enum Repr {
+         Secs(u64),
+         Human(String),
+         Map { duration_s: u64 },
+      }
+      match Repr::deserialize(deserializer)? {
+         Repr::Secs(duration_s) | Repr::Map { duration_s } => Ok(Timespan::new(duration_s)),
+         Repr::Human(s) => Timespan::from_human(&s).map_err(serde::de::Error::custom),
+      }
    }
 }
 
+/// How far ahead (or back) a view looks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Horizon {
    Day,
    Week,
@@ -105,40 +357,424 @@ pub enum Horizon {
    Year,
    Lifetime,
 }
+impl Horizon {
+   /// The approximate length of the horizon; months are 30 days, years
+   /// are 365 and a lifetime is a century.
+   pub fn as_timespan(&self) -> Timespan {
+      const DAY_S: u64 = 86400;
+      match self {
+         Horizon::Day => Timespan::new(DAY_S),
+         Horizon::Week => Timespan::new(7 * DAY_S),
+         Horizon::Month => Timespan::new(30 * DAY_S),
+         Horizon::Year => Timespan::new(365 * DAY_S),
+         Horizon::Lifetime => Timespan::new(100 * 365 * DAY_S),
+      }
+   }
+   /// Whether `ts` lies within the horizon either side of `now`.
+   pub fn bucket(&self, ts: DTUtc, now: DTUtc) -> bool {
+      (ts - now).num_seconds().unsigned_abs() <= self.as_timespan().as_secs()
+   }
+}
+
+/// Horizons from the nearest to the furthest.
+const HORIZONS: [Horizon; 5] = [
+   Horizon::Day,
+   Horizon::Week,
+   Horizon::Month,
+   Horizon::Year,
+   Horizon::Lifetime,
+];
 
 #[derive(Debug, Clone, Deserialize, Serialize, Ord, Eq, PartialOrd, PartialEq)]
+#[serde(deny_unknown_fields)]
 pub struct ListV1 {
+   #[serde(default = "migrate::default_version")]
+   pub version: u32,
    pub name: String,
+   #[serde(default = "migrate::default_ts")]
+   pub created_ts: DTUtc,
+   /// Set on every save.
+   #[serde(default = "migrate::default_ts")]
+   pub modified_ts: DTUtc,
    pub items: Vec<ListItem>,
 }
 impl ListV1 {
    fn new(name: &str) -> ListV1 {
+      let now = Utc::now();
       ListV1 {
+         version: migrate::VERSION,
          name: name.to_string(),
+         created_ts: now,
+         modified_ts: now,
          items: vec![],
       }
    }
+   /// A list to nest in a timeline, with the timestamps it reads back
+   /// with.
+   fn sublist(name: &str) -> ListV1 {
+      ListV1 {
+         created_ts: migrate::sublist_ts(),
+         modified_ts: migrate::sublist_ts(),
+         ..ListV1::new(name)
+      }
+   }
    fn filename(name: &str) -> String {
-      format!("{}{}", &name, IFNOTNOW_EXTENSION)
+      store_dir()
+         .join(format!("{}{}", &name, IFNOTNOW_EXTENSION))
+         .to_string_lossy()
+         .to_string()
    }
    fn load(name: &str) -> Result<ListV1, INNError> {
-      match std::fs::File::open(ListV1::filename(&name)) {
-         Ok(file) => {
-            let reader = std::io::BufReader::new(file);
-            match serde_yaml::from_reader(reader) {
-               Ok(l) => Ok(l),
-               Err(e) => Err(INNError::Yaml(e)),
-            }
+      let _lock = lock::Lock::acquire(&ListV1::filename(name))?;
+      debug!("reading {}", ListV1::filename(name));
+      match std::fs::read_to_string(ListV1::filename(name)) {
+         // The file decides the name so that saving writes back to it.
+         Ok(text) => Ok(ListV1 {
+            name: name.to_string(),
+            ..migrate::migrate(&text, &ListV1::filename(name))?
+         }),
+         Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            Err(INNError::NotFound(ListV1::filename(name)))
          }
          Err(e) => Err(INNError::File(e)),
       }
    }
+   /// Maps each tag to the indices of the items that carry it. An item in
+   /// a sublist counts for the sublist's index.
+   pub fn tags(&self) -> BTreeMap<String, Vec<usize>> {
+      self.index_by(&ListItem::tags)
+   }
+   /// Every tag used, sublists included, sorted.
+   pub fn unique_tags(&self) -> BTreeSet<String> {
+      self.tags().into_keys().collect()
+   }
+   /// Maps each `@person` mentioned to the indices of the items that
+   /// mention them, counted like `tags`.
+   pub fn people(&self) -> BTreeMap<String, Vec<usize>> {
+      self.index_by(&ListItem::people)
+   }
+   fn index_by(&self, keys: &dyn Fn(&ListItem) -> Vec<Mention>) -> BTreeMap<String, Vec<usize>> {
+      fn collect(
+         list: &ListV1,
+         idx: Option<usize>,
+         keys: &dyn Fn(&ListItem) -> Vec<Mention>,
+         index: &mut BTreeMap<String, Vec<usize>>,
+      ) {
+         for (i, item) in list.items.iter().enumerate() {
+            let idx = idx.unwrap_or(i);
+            match item {
+               ListItem::Sublist(sub) => collect(sub, Some(idx), keys, index),
+               _ => {
+                  for key in keys(item) {
+                     let indices = index.entry(key.into()).or_default();
+                     if indices.last() != Some(&idx) {
+                        indices.push(idx);
+                     }
+                  }
+               }
+            }
+         }
+      }
+      let mut index = BTreeMap::new();
+      collect(self, None, keys, &mut index);
+      index
+   }
+   /// A copy holding only the items that are kept, looking into sublists
+   /// and dropping those left empty.
+   pub fn retain_items(&self, keep: &dyn Fn(&ListItem) -> bool) -> ListV1 {
+      let mut items = vec![];
+      for item in self.items.iter() {
+         match item {
+            ListItem::Sublist(sub) => {
+               let sub = sub.retain_items(keep);
+               if !sub.items.is_empty() {
+                  items.push(ListItem::Sublist(sub));
+               }
+            }
+            _ if keep(item) => items.push(item.clone()),
+            _ => {}
+         }
+      }
+      ListV1 {
+         version: self.version,
+         name: self.name.to_string(),
+         created_ts: self.created_ts,
+         modified_ts: self.modified_ts,
+         items,
+      }
+   }
+   /// How many items there are besides sublists, counting into them.
+   pub fn count_items(&self) -> usize {
+      self
+         .items
+         .iter()
+         .map(|item| match item {
+            ListItem::Sublist(sub) => sub.count_items(),
+            _ => 1,
+         })
+         .sum()
+   }
+   /// What is wrong in a timeline that loads: timeboxes whose accrued
+   /// time disagrees with their history and goals without a label.
+   /// Running timeboxes accrue as time passes and are not checked.
+   pub fn problems(&self) -> Vec<String> {
+      fn goal_problems(goal: &Goal, at: &str, out: &mut Vec<String>) {
+         if goal.label.trim().is_empty() {
+            out.push(format!("{}: goal has an empty label", at));
+         }
+         for (n, sub) in goal.subtasks.iter().enumerate() {
+            goal_problems(sub, &format!("{}.{}", at, n + 1), out);
+         }
+      }
+      fn list_problems(list: &ListV1, prefix: &str, out: &mut Vec<String>) {
+         for (n, item) in list.items.iter().enumerate() {
+            let at = format!("{}{}", prefix, n + 1);
+            match item {
+               ListItem::Goal(goal) => goal_problems(goal, &at, out),
+               ListItem::Sublist(sub) => list_problems(sub, &format!("{}/", at), out),
+               _ => {}
+            }
+         }
+      }
+      let mut out = vec![];
+      list_problems(self, "", &mut out);
+      for drift in self.drift() {
+         out.push(format!(
+            "{}: {:?} has {} accrued but its history adds up to {}",
+            drift.at, drift.label, drift.stored, drift.recomputed
+         ));
+      }
+      out
+   }
+   /// The timeboxes whose stored `accrued` is not what their history
+   /// adds up to. Running timeboxes keep accruing, so they are left out.
+   pub fn drift(&self) -> Vec<Drift> {
+      fn list_drift(list: &ListV1, prefix: &str, out: &mut Vec<Drift>) {
+         for (n, item) in list.items.iter().enumerate() {
+            let at = format!("{}{}", prefix, n + 1);
+            match item {
+               ListItem::Timebox(tb) if !tb.is_running() => {
+                  let recomputed = tb.recompute_accrued();
+                  if tb.accrued != recomputed {
+                     out.push(Drift {
+                        at,
+                        label: tb.label.clone(),
+                        stored: tb.accrued.clone(),
+                        recomputed,
+                     });
+                  }
+               }
+               ListItem::Sublist(sub) => list_drift(sub, &format!("{}/", at), out),
+               _ => {}
+            }
+         }
+      }
+      let mut out = vec![];
+      list_drift(self, "", &mut out);
+      out
+   }
+   /// Sets `accrued` from the history of each timebox that is not
+   /// running.
+   pub fn fix_drift(&mut self) {
+      for item in self.items.iter_mut() {
+         match item {
+            ListItem::Timebox(tb) if !tb.is_running() => tb.accrued = tb.recompute_accrued(),
+            ListItem::Sublist(sub) => sub.fix_drift(),
+            _ => {}
+         }
+      }
+   }
+   /// Takes the timeline back to how it would be before any work on it:
+   /// goals open, timeboxes unstarted and everything created now.
+   /// Schedules and recurrences are kept.
+   pub fn reset(&mut self) {
+      fn reset_goal(goal: &mut Goal) {
+         goal.done = false;
+         goal.history.clear();
+         goal.subtasks.iter_mut().for_each(reset_goal);
+      }
+      let now = Utc::now();
+      self.created_ts = now;
+      self.modified_ts = now;
+      for item in self.items.iter_mut() {
+         match item {
+            ListItem::Goal(goal) => reset_goal(goal),
+            ListItem::Timebox(tb) => {
+               tb.done = None;
+               tb.accrued = Timespan::new(0);
+               tb.history = vec![AttentionEvent::Created(now)];
+            }
+            ListItem::Sublist(sub) => sub.reset(),
+            _ => {}
+         }
+      }
+   }
+   /// Finds the item for a 1-based index, an exact label or a
+   /// case-insensitive substring of exactly one label.
+   pub fn find_item(&self, selector: &str) -> Result<usize, SelectError> {
+      if let Ok(n) = selector.parse::<usize>() {
+         if n >= 1 && n <= self.items.len() {
+            return Ok(n - 1);
+         }
+      }
+      let exact: Vec<usize> = (0..self.items.len())
+         .filter(|idx| self.items[*idx].label() == selector)
+         .collect();
+      match exact.as_slice() {
+         [idx] => return Ok(*idx),
+         [] => {}
+         _ => return Err(SelectError::Ambiguous(exact)),
+      }
+      let needle = selector.to_lowercase();
+      let candidates: Vec<usize> = (0..self.items.len())
+         .filter(|idx| self.items[*idx].label().to_lowercase().contains(&needle))
+         .collect();
+      match candidates.as_slice() {
+         [idx] => Ok(*idx),
+         [] => Err(SelectError::NotFound),
+         _ => Err(SelectError::Ambiguous(candidates)),
+      }
+   }
+   /// Writes the timeline to a temporary file and renames it over the
+   /// timeline's file, so a failed write never leaves a truncated file.
+   /// Journals the file for undo, then overwrites it. A dry run only
+   /// reports what would change.
+   fn save(&self) -> Result<(), INNError> {
+      if dryrun::dry_run() {
+         return dryrun::report(self);
+      }
+      let _lock = lock::Lock::acquire(&ListV1::filename(&self.name))?;
+      debug!("writing {}", ListV1::filename(&self.name));
+      let timeline_yaml = normalize_yaml(&serde_yaml::to_string(&ListV1 {
+         modified_ts: Utc::now(),
+         ..self.clone()
+      })?);
+      undo::record(&self.name)?;
+      write_atomically(&ListV1::filename(&self.name), &timeline_yaml)
+   }
+}
+impl Matchable for ListV1 {
+   fn matches(&self, pattern: Pattern) -> Result<usize, PatternErr> {
+      let mut hits = 0;
+      for item in self.items.iter() {
+         hits += item.matches(pattern.clone())?;
+      }
+      Ok(hits)
+   }
+}
+
+/// Why a selector picked no single item. `Ambiguous` holds the indices
+/// of the items it could mean.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SelectError {
+   NotFound,
+   Ambiguous(Vec<usize>),
 }
 
 #[derive(Debug)]
 pub enum INNError {
    Yaml(serde_yaml::Error),
+   Json(serde_json::Error),
    File(std::io::Error),
+   Pattern(PatternErr),
+   NotFound(String),
+   NoSuchItem(String),
+   AmbiguousItem(String, Vec<String>),
+   NotAGoal(String),
+   NotATimebox(String),
+   NotRunning(String),
+   BadDuration(String),
+   UnknownVersion(String),
+   NoNowContext,
+   BadTime(String),
+   EndsBeforeBegins,
+   NotSchedulable(String),
+   NothingToUndo(String),
+   NoSuchPosition(String, usize),
+   BadConfig(String, serde_yaml::Error),
+   EditorFailed(String),
+   Invalid(usize),
+   BadCount(String),
+   AlreadyExists(String),
+   Locked(String),
+   NoSuchTemplate(String),
+   EmptyPattern,
+   UnknownField(String, String, usize),
+   TuiOnly(String),
+   ReservedName(String),
+   RecursTooFar(recurrence::Recurrence),
+}
+impl fmt::Display for INNError {
+   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+      match self {
+         INNError::Yaml(e) => write!(f, "malformed timeline: {}", e),
+         INNError::Json(e) => write!(f, "{}", e),
+         INNError::File(e) => write!(f, "{}", e),
+         INNError::Pattern(PatternErr::InvalidRegex(e)) => write!(f, "invalid regex: {}", e),
+         INNError::NotFound(filename) => write!(f, "{} not found", filename),
+         INNError::NoSuchItem(selector) => write!(f, "no item matches {:?}", selector),
+         INNError::AmbiguousItem(selector, labels) => {
+            write!(f, "{:?} matches {}", selector, labels.join(", "))
+         }
+         INNError::NotAGoal(label) => write!(f, "{:?} is not a goal", label),
+         INNError::NotATimebox(label) => write!(f, "{:?} is not a timebox", label),
+         INNError::NotRunning(label) => write!(f, "{:?} is not started", label),
+         INNError::UnknownVersion(v) => write!(f, "unknown timeline version {}", v),
+         INNError::BadDuration(s) => write!(f, "{:?} is not a duration like 1h30m", s),
+         INNError::NoNowContext => write!(f, "no timeline is set for now, try: now NAME"),
+         INNError::BadTime(s) => write!(f, "{:?} is not a time like 2021-03-01T09:00:00Z", s),
+         INNError::EndsBeforeBegins => write!(f, "the schedule ends before it begins"),
+         INNError::NoSuchPosition(pos, len) => {
+            write!(f, "{:?} is not a position from 1 to {}", pos, len)
+         }
+         INNError::BadConfig(filename, e) => write!(f, "malformed config {}: {}", filename, e),
+         INNError::EditorFailed(editor) => write!(f, "{} did not exit cleanly", editor),
+         INNError::Invalid(n) => write!(f, "{} timeline(s) did not validate", n),
+         INNError::Locked(filename) => write!(
+            f,
+            "{} is held by another inn; remove it if none is running",
+            filename
+         ),
+         INNError::AlreadyExists(name) => write!(f, "{} already exists", name),
+         INNError::EmptyPattern => write!(f, "the pattern is empty and would match everything"),
+         INNError::TuiOnly(what) => write!(f, "{} only works in the tui", what),
+         INNError::ReservedName(name) => {
+            write!(f, "{:?} is the name of a file inn keeps for itself, pick another", name)
+         }
+         INNError::RecursTooFar(recurrence) => {
+            write!(f, "{:?} comes due past the last date inn can schedule", recurrence)
+         }
+         INNError::UnknownField(filename, field, line) => write!(
+            f,
+            "{} line {}: unknown field {:?}, check its spelling or remove it",
+            filename, line, field
+         ),
+         INNError::NoSuchTemplate(name) => {
+            write!(f, "no template named {:?}, see: templates", name)
+         }
+         INNError::BadCount(s) => write!(f, "{:?} is not a whole number like 0, 1 or 20", s),
+         INNError::NothingToUndo(name) => write!(f, "nothing to undo in {}", name),
+         INNError::NotSchedulable(label) => {
+            write!(f, "{:?} is neither a goal nor a timebox to schedule", label)
+         }
+      }
+   }
+}
+impl std::error::Error for INNError {}
+impl From<std::io::Error> for INNError {
+   fn from(e: std::io::Error) -> INNError {
+      INNError::File(e)
+   }
+}
+impl From<serde_yaml::Error> for INNError {
+   fn from(e: serde_yaml::Error) -> INNError {
+      INNError::Yaml(e)
+   }
+}
+impl From<serde_json::Error> for INNError {
+   fn from(e: serde_json::Error) -> INNError {
+      INNError::Json(e)
+   }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, PartialOrd, Ord, Eq)]
@@ -150,25 +786,126 @@ pub enum AttentionEvent {
    Abandoned(DTUtc),
    Finished(DTUtc),
 }
+impl AttentionEvent {
+   /// When the event happened.
+   pub fn at(&self) -> DTUtc {
+      use AttentionEvent::*;
+      match self {
+         Created(ts) | Started(ts) | Paused(ts) | Abandoned(ts) | Finished(ts) => *ts,
+         WaitingFor(ts, _) => *ts,
+      }
+   }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, PartialOrd, Ord, Eq)]
+#[serde(deny_unknown_fields)]
 pub struct Goal {
    pub label: String,
    pub done: bool,
+   #[serde(default, skip_serializing_if = "Vec::is_empty")]
+   pub subtasks: Vec<Goal>,
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   pub scheduled: Option<Event>,
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   pub recurrence: Option<recurrence::Recurrence>,
+   /// When the goal was done, recorded by `done` and as a recurring goal
+   /// is rolled over. `done` stays the current state.
+   #[serde(default, skip_serializing_if = "Vec::is_empty")]
+   pub history: Vec<AttentionEvent>,
 }
 impl Goal {
    fn new(label: String, done: bool) -> Goal {
-      Goal { label, done }
+      Goal {
+         label,
+         done,
+         subtasks: vec![],
+         scheduled: None,
+         recurrence: None,
+         history: vec![],
+      }
+   }
+   /// Marks the goal done or open. Getting done records a `Finished`, so
+   /// the history tells when; reopening leaves the history alone.
+   pub fn set_done(&mut self, done: bool, now: DTUtc) {
+      if done && !self.done {
+         self.history.push(AttentionEvent::Finished(now));
+      }
+      self.done = done;
+   }
+   /// Whether a `Finished` is recorded at or after `from` and before
+   /// `until`.
+   pub fn finished_between(&self, from: DTUtc, until: DTUtc) -> bool {
+      self.history.iter().any(|event| match event {
+         AttentionEvent::Finished(ts) => *ts >= from && *ts < until,
+         _ => false,
+      })
+   }
+   /// A goal is done when marked so or when all of its subtasks are.
+   pub fn is_done(&self) -> bool {
+      self.done || (!self.subtasks.is_empty() && self.subtasks.iter().all(|sub| sub.is_done()))
+   }
+}
+
+/// A reference to another timeline, or to one of its items by its
+/// index from 1.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, PartialOrd, Ord, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Link {
+   pub target: String,
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   pub item: Option<usize>,
+   pub label: String,
+}
+impl Link {
+   /// A link is broken when its timeline does not load or has no item at
+   /// its index. Links are only checked when shown, so a dangling one
+   /// does not keep its own timeline from loading.
+   pub fn is_broken(&self, targets: &LinkTargets) -> bool {
+      match targets.0.get(&self.target) {
+         Some(Some(len)) => self.item.is_some_and(|item| item == 0 || item > *len),
+         _ => true,
+      }
+   }
+}
+
+/// The number of items in each timeline a list links to, or `None` for
+/// one that does not load. Resolved once for a render rather than for
+/// every link shown.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LinkTargets(BTreeMap<String, Option<usize>>);
+impl LinkTargets {
+   /// Loads each timeline the list or its sublists link to, once.
+   pub fn resolve(list: &ListV1) -> LinkTargets {
+      fn collect(list: &ListV1, targets: &mut BTreeMap<String, Option<usize>>) {
+         for item in list.items.iter() {
+            match item {
+               ListItem::Link(link) if !targets.contains_key(&link.target) => {
+                  let len = ListV1::load(&link.target).ok().map(|t| t.items.len());
+                  targets.insert(link.target.to_string(), len);
+               }
+               ListItem::Sublist(sub) => collect(sub, targets),
+               _ => {}
+            }
+         }
+      }
+      let mut targets = BTreeMap::new();
+      collect(list, &mut targets);
+      LinkTargets(targets)
    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, PartialOrd, Ord, Eq)]
+#[serde(deny_unknown_fields)]
 pub struct CheckTimebox {
    pub label: String,
    pub done: Option<DTUtc>,
    pub history: Vec<AttentionEvent>,
    pub accrued: Timespan,
    pub budget: Timespan,
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   pub scheduled: Option<Event>,
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   pub recurrence: Option<recurrence::Recurrence>,
 }
 impl CheckTimebox {
    fn new(label: String, done: Option<DTUtc>) -> CheckTimebox {
@@ -178,7 +915,182 @@ impl CheckTimebox {
          accrued: Timespan::new(0),
          budget: Timespan::new(3600),
          history: vec![AttentionEvent::Created(Utc::now())],
+         scheduled: None,
+         recurrence: None,
+      }
+   }
+   /// Sums the time spent between each `Started` and the event that
+   /// ends it. Waiting is not accrued, and a timebox that is still
+   /// running accrues up to now.
+   pub fn recompute_accrued(&self) -> Timespan {
+      self.accrued_at(Utc::now())
+   }
+   /// What `--sort label` orders timeboxes by: the label in any case,
+   /// then when they were done, with open ones first. History and
+   /// accrued time change as a timebox is worked on and play no part,
+   /// unlike in `Ord`.
+   pub fn sort_key(&self) -> (String, Option<DTUtc>) {
+      (self.label.to_lowercase(), self.done)
+   }
+   /// `recompute_accrued` as of `now`.
+   pub fn accrued_at(&self, now: DTUtc) -> Timespan {
+      self.accrued_between(None, now)
+   }
+   /// The time accrued from `from`, or from the start, up to `until`.
+   /// Spans that cross either end count only the part inside.
+   pub fn accrued_between(&self, from: Option<DTUtc>, until: DTUtc) -> Timespan {
+      let mut accrued_s = 0;
+      for (begins, ends) in self.spans() {
+         let begins = from.map_or(begins, |from| begins.max(from));
+         let ends = ends.unwrap_or(until).min(until);
+         accrued_s += (ends - begins).num_seconds().max(0) as u64;
+      }
+      Timespan::new(accrued_s)
+   }
+   /// When the span still open in the history was started.
+   pub fn running_since(&self) -> Option<DTUtc> {
+      match self.spans().last() {
+         Some((since, None)) => Some(*since),
+         _ => None,
+      }
+   }
+   /// Each `Started` paired with the event that ends it, the last one
+   /// without an end while the timebox is running.
+   fn spans(&self) -> Vec<(DTUtc, Option<DTUtc>)> {
+      let mut history = self.history.clone();
+      history.sort_by_key(|event| event.at());
+      let mut spans = vec![];
+      let mut started: Option<DTUtc> = None;
+      for event in history.iter() {
+         match event {
+            AttentionEvent::Created(_) => {}
+            AttentionEvent::Started(ts) => {
+               if started.is_none() {
+                  started = Some(*ts);
+               }
+            }
+            AttentionEvent::Paused(ts)
+            | AttentionEvent::WaitingFor(ts, _)
+            | AttentionEvent::Abandoned(ts)
+            | AttentionEvent::Finished(ts) => {
+               if let Some(since) = started.take() {
+                  spans.push((since, Some(*ts)));
+               }
+            }
+         }
       }
+      if let Some(since) = started {
+         spans.push((since, None));
+      }
+      spans
+   }
+   /// A timebox is running when its latest event is a `Started`.
+   pub fn is_running(&self) -> bool {
+      let latest = self
+         .history
+         .iter()
+         .filter(|event| !matches!(event, AttentionEvent::Created(_)))
+         .max_by_key(|event| event.at());
+      matches!(latest, Some(AttentionEvent::Started(_)))
+   }
+   /// How far the accrued time has gone past the budget, if it has.
+   pub fn overage(&self) -> Option<Timespan> {
+      let accrued = self.recompute_accrued();
+      if accrued > self.budget {
+         Some(accrued.saturating_sub(&self.budget))
+      } else {
+         None
+      }
+   }
+   /// When anything last happened to the timebox.
+   pub fn last_activity(&self) -> Option<DTUtc> {
+      self.history.iter().map(|event| event.at()).max()
+   }
+   /// The reason a timebox is blocked, when its latest event is a
+   /// `WaitingFor`. Starting it again ends the wait.
+   pub fn waiting_for(&self) -> Option<&str> {
+      let latest = self
+         .history
+         .iter()
+         .filter(|event| !matches!(event, AttentionEvent::Created(_)))
+         .max_by_key(|event| event.at());
+      match latest {
+         Some(AttentionEvent::WaitingFor(_, reason)) => Some(reason),
+         _ => None,
+      }
+   }
+}
+
+/// A goal counts the matches in its own label and in those of its
+/// subtasks, each label once.
+impl Matchable for Goal {
+   fn matches(&self, pattern: Pattern) -> Result<usize, PatternErr> {
+      let mut hits = pattern.count_in(&self.label)?;
+      for sub in self.subtasks.iter() {
+         hits += sub.matches(pattern.clone())?;
+      }
+      Ok(hits)
+   }
+   /// The spans are in the goal's own label; each subtask has its own.
+   fn match_spans(&self, pattern: &Pattern) -> Result<Vec<(usize, usize)>, PatternErr> {
+      pattern.spans_in(&self.label)
+   }
+}
+
+/// The reasons given for waiting are searched along with the label, but
+/// not the timestamps.
+impl Matchable for CheckTimebox {
+   fn matches(&self, pattern: Pattern) -> Result<usize, PatternErr> {
+      let mut hits = pattern.count_in(&self.label)?;
+      for event in self.history.iter() {
+         if let AttentionEvent::WaitingFor(_, reason) = event {
+            hits += pattern.count_in(reason)?;
+         }
+      }
+      Ok(hits)
+   }
+   /// The spans are in `searched_text`.
+   fn match_spans(&self, pattern: &Pattern) -> Result<Vec<(usize, usize)>, PatternErr> {
+      pattern.spans_in(&self.searched_text())
+   }
+}
+
+impl CheckTimebox {
+   /// The label and then each reason given for waiting, one per line.
+   pub fn searched_text(&self) -> String {
+      let mut text = self.label.clone();
+      for event in self.history.iter() {
+         if let AttentionEvent::WaitingFor(_, reason) = event {
+            text.push('\n');
+            text.push_str(reason);
+         }
+      }
+      text
+   }
+}
+
+/// A `#tag`, `@person` or `+project` named in an item's text, lowercased
+/// and without its marker, so that each spelling of it is the same one.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Mention(String);
+impl Mention {
+   pub fn as_str(&self) -> &str {
+      &self.0
+   }
+}
+impl fmt::Display for Mention {
+   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      write!(f, "{}", self.0)
+   }
+}
+impl From<Mention> for String {
+   fn from(mention: Mention) -> String {
+      mention.0
+   }
+}
+impl PartialEq<&str> for Mention {
+   fn eq(&self, other: &&str) -> bool {
+      self.0 == *other
    }
 }
 
@@ -188,226 +1100,3607 @@ pub enum ListItem {
    Entry(String),
    Goal(Goal),
    Timebox(CheckTimebox),
+   #[serde(with = "migrate::sublist")]
    Sublist(ListV1),
    Note(String),
+   Place(geoview::GeoPoint),
+   Link(Link),
 }
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ListMap {
-   lmap: BTreeMap<String, ListV1>,
-}
-impl ListMap {
-   fn new() -> ListMap {
-      ListMap {
-         lmap: BTreeMap::new(),
+impl ListItem {
+   /// When a scheduled item begins, or ends if it has no beginning.
+   /// Otherwise when the item was created, for the items that keep
+   /// track of it.
+   pub fn timestamp(&self) -> Option<DTUtc> {
+      match self {
+         ListItem::Goal(goal) => goal.scheduled.as_ref().and_then(Event::at),
+         ListItem::Timebox(tb) => tb.scheduled.as_ref().and_then(Event::at).or_else(|| {
+            tb.history.iter().find_map(|event| match event {
+               AttentionEvent::Created(ts) => Some(*ts),
+               _ => None,
+            })
+         }),
+         _ => None,
       }
    }
-   fn add(&mut self, listname: &str) {
-      self
-         .lmap
-         .insert(listname.to_string(), ListV1::new(listname));
+   /// The `#tags` in the labels of goals and timeboxes and the text of
+   /// entries and notes, lowercased and without the `#`.
+   pub fn tags(&self) -> Vec<Mention> {
+      self.marked('#')
    }
-   fn drop(&mut self, listname: &str) {
-      self.lmap.remove(&listname.to_string());
+   /// The `@people` mentioned like tags. The `@` has to start a word, so
+   /// an address like `me@example.com` mentions nobody.
+   pub fn people(&self) -> Vec<Mention> {
+      self.marked('@')
    }
-}
-
-#[derive(Debug, Clone, Serialize, Eq, Ord, PartialEq, PartialOrd)]
-pub struct Event {
-   list: ListV1,
-   created_ts: DTUtc,
-   begins: Option<DTUtc>,
-   ends: Option<DTUtc>,
-   span: Option<Timespan>,
-}
-impl Event {
-   fn new(list: ListV1, span: Timespan) -> Event {
-      Event {
-         list,
-         span: Some(span),
-         begins: None,
-         ends: None,
+   /// The todo.txt `+projects` the text names, found like tags.
+   pub fn projects(&self) -> Vec<Mention> {
+      self.marked('+')
+   }
+   fn marked(&self, marker: char) -> Vec<Mention> {
+      let text = match self {
+         ListItem::Goal(goal) => &goal.label,
+         ListItem::Timebox(tb) => &tb.label,
+         ListItem::Entry(txt) | ListItem::Note(txt) => txt,
+         _ => return vec![],
+      };
+      let marker = regex::escape(&marker.to_string());
+      let re = regex::Regex::new(&format!(r"(?:^|\s){}([\w-]+)", marker)).unwrap();
+      // An item mentioning the same thing twice has it once.
+      let mut seen = HashSet::new();
+      re.captures_iter(text)
+         .map(|cap| Mention(cap[1].to_lowercase()))
+         .filter(|mention| seen.insert(mention.clone()))
+         .collect()
+   }
+   /// Whether a goal or a timebox is done; other items have no status.
+   pub fn is_done(&self) -> Option<bool> {
+      match self {
+         ListItem::Goal(goal) => Some(goal.is_done()),
+         ListItem::Timebox(tb) => Some(tb.done.is_some()),
+         _ => None,
+      }
+   }
+   /// When a done timebox was finished, or when a done goal last got a
+   /// `Finished` in its history.
+   pub fn finished_at(&self) -> Option<DTUtc> {
+      match self {
+         ListItem::Timebox(tb) => tb.done,
+         ListItem::Goal(goal) if goal.is_done() => goal
+            .history
+            .iter()
+            .filter_map(|event| match event {
+               AttentionEvent::Finished(ts) => Some(*ts),
+               _ => None,
+            })
+            .max(),
+         _ => None,
+      }
+   }
+   /// The text that identifies the item.
+   pub fn label(&self) -> &str {
+      match self {
+         ListItem::Heading(txt) | ListItem::Entry(txt) | ListItem::Note(txt) => txt,
+         ListItem::Goal(goal) => &goal.label,
+         ListItem::Timebox(tb) => &tb.label,
+         ListItem::Sublist(sub) => &sub.name,
+         ListItem::Place(p) => &p.label,
+         ListItem::Link(link) => &link.label,
+      }
+   }
+   pub fn label_mut(&mut self) -> &mut String {
+      match self {
+         ListItem::Heading(txt) | ListItem::Entry(txt) | ListItem::Note(txt) => txt,
+         ListItem::Goal(goal) => &mut goal.label,
+         ListItem::Timebox(tb) => &mut tb.label,
+         ListItem::Sublist(sub) => &mut sub.name,
+         ListItem::Place(p) => &mut p.label,
+         ListItem::Link(link) => &mut link.label,
+      }
+   }
+   /// Reads a todo.txt task as a goal. The priority and the dates stay
+   /// in the label since goals have no fields for them yet, so
+   /// `to_todotxt` writes them back.
+   pub fn from_todotxt(task: todotxt::Task) -> ListItem {
+      let mut label = String::new();
+      if let Some(p) = task.priority {
+         label.push_str(&format!("({}) ", p));
+      }
+      for date in task.completed.iter().chain(task.created.iter()) {
+         label.push_str(&format!("{} ", date.format("%Y-%m-%d")));
+      }
+      label.push_str(&task.text);
+      ListItem::Goal(Goal::new(label, task.done))
+   }
+   /// Writes a goal as a todo.txt line; other items have no equivalent.
+   pub fn to_todotxt(&self) -> Option<String> {
+      match self {
+         ListItem::Goal(goal) if goal.done => Some(format!("x {}", goal.label)),
+         ListItem::Goal(goal) => Some(goal.label.to_string()),
+         _ => None,
+      }
+   }
+}
+impl Matchable for ListItem {
+   fn matches(&self, pattern: Pattern) -> Result<usize, PatternErr> {
+      match self {
+         ListItem::Heading(txt) | ListItem::Entry(txt) | ListItem::Note(txt) => {
+            pattern.count_in(txt)
+         }
+         ListItem::Goal(goal) => goal.matches(pattern),
+         ListItem::Timebox(tb) => tb.matches(pattern),
+         ListItem::Sublist(sub) => sub.matches(pattern),
+         ListItem::Place(p) => pattern.count_in(&p.label),
+         ListItem::Link(link) => pattern.count_in(&link.label),
+      }
+   }
+   /// The spans are in the label, or a timebox's `searched_text`.
+   /// Sublists have no text of their own, their items do.
+   fn match_spans(&self, pattern: &Pattern) -> Result<Vec<(usize, usize)>, PatternErr> {
+      match self {
+         ListItem::Sublist(_) => Ok(vec![]),
+         ListItem::Timebox(tb) => tb.match_spans(pattern),
+         _ => pattern.spans_in(self.label()),
+      }
+   }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ListMap {
+   lmap: BTreeMap<String, ListV1>,
+}
+impl ListMap {
+   fn new() -> ListMap {
+      ListMap {
+         lmap: BTreeMap::new(),
+      }
+   }
+   /// Adds an empty timeline, unless one of that name is already here.
+   fn add(&mut self, listname: &str) -> Result<(), INNError> {
+      if self.contains(listname) {
+         return Err(INNError::AlreadyExists(listname.to_string()));
+      }
+      self.add_or_replace(listname);
+      Ok(())
+   }
+   /// Adds an empty timeline, dropping any of the same name.
+   fn add_or_replace(&mut self, listname: &str) {
+      self
+         .lmap
+         .insert(listname.to_string(), ListV1::new(listname));
+   }
+   pub fn contains(&self, name: &str) -> bool {
+      self.lmap.contains_key(name)
+   }
+   #[allow(dead_code)]
+   fn drop(&mut self, listname: &str) {
+      self.lmap.remove(listname);
+   }
+   fn insert(&mut self, list: ListV1) {
+      self.lmap.insert(list.name.to_string(), list);
+   }
+   pub fn get(&self, name: &str) -> Option<&ListV1> {
+      self.lmap.get(name)
+   }
+   pub fn get_mut(&mut self, name: &str) -> Option<&mut ListV1> {
+      self.lmap.get_mut(name)
+   }
+   /// The timelines in the order of their names.
+   pub fn iter(&self) -> impl Iterator<Item = &ListV1> {
+      self.lmap.values()
+   }
+   /// The names of the timelines, sorted.
+   pub fn names(&self) -> Vec<&str> {
+      self.lmap.keys().map(|name| name.as_str()).collect()
+   }
+}
+
+/// Something happening to a list over a span of time. Scheduled items
+/// keep their event in the timeline file, so it is read back as well as
+/// written, and the times it leaves out load as `None`.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, Ord, PartialEq, PartialOrd)]
+#[serde(deny_unknown_fields)]
+pub struct Event {
+   list: ListV1,
+   created_ts: DTUtc,
+   begins: Option<DTUtc>,
+   ends: Option<DTUtc>,
+   span: Option<Timespan>,
+}
+impl Event {
+   fn new(list: ListV1, span: Timespan) -> Event {
+      Event {
+         list,
+         span: Some(span),
+         begins: None,
+         ends: None,
+         created_ts: Utc::now(),
+      }
+   }
+   /// An event from `begins` to `ends`, spanning the time between them
+   /// when both are given.
+   fn scheduled(
+      list: ListV1,
+      begins: Option<DTUtc>,
+      ends: Option<DTUtc>,
+   ) -> Result<Event, INNError> {
+      let span = match (begins, ends) {
+         (Some(b), Some(e)) if e < b => return Err(INNError::EndsBeforeBegins),
+         (Some(b), Some(e)) => Some(Timespan::new((e - b).num_seconds() as u64)),
+         _ => None,
+      };
+      Ok(Event {
+         list,
+         span,
+         begins,
+         ends,
          created_ts: Utc::now(),
+      })
+   }
+   /// When the event begins, or ends if it has no beginning.
+   fn at(&self) -> Option<DTUtc> {
+      self.begins.or(self.ends)
+   }
+   /// Records the event as a timebox named after its list, budgeted for
+   /// its span and carrying its begin and end.
+   fn to_timebox(&self) -> CheckTimebox {
+      let mut tb = CheckTimebox::new(self.list.name.to_string(), self.ends);
+      tb.history = vec![AttentionEvent::Created(self.created_ts)];
+      if let Some(span) = &self.span {
+         tb.budget = span.clone();
+      }
+      if let Some(begins) = self.begins {
+         tb.history.push(AttentionEvent::Started(begins));
+      }
+      if let Some(ends) = self.ends {
+         tb.history.push(AttentionEvent::Finished(ends));
+      }
+      tb.accrued = tb.recompute_accrued();
+      tb
+   }
+}
+
+fn main() {
+   if let Err(e) = run(std::env::args_os()) {
+      error!("{}", e);
+      std::process::exit(1);
+   }
+}
+
+/// The command line of ifnotnow, shared by `run` and `completions`.
+fn build_cli() -> App<'static, 'static> {
+   App::new("ifnotnow")
+      .version("1.0")
+      .author("Simon Janes <spjanes@protonmail.com>")
+      .arg(
+         Arg::with_name("dir")
+            .long("dir")
+            .help("Directory of the timelines [default: current directory]")
+            .env("INN_DIR")
+            .global(true)
+            .takes_value(true),
+      )
+      .arg(
+         Arg::with_name("quiet")
+            .short("q")
+            .long("quiet")
+            .help("Prints only what was asked for and errors")
+            .conflicts_with("verbose")
+            .global(true),
+      )
+      .arg(
+         Arg::with_name("verbose")
+            .short("v")
+            .long("verbose")
+            .help("Also traces the files read and written and the items matched")
+            .global(true),
+      )
+      .arg(
+         Arg::with_name("dry-run")
+            .long("dry-run")
+            .help("Prints what would change on stderr instead of writing any files")
+            .global(true),
+      )
+      .arg(
+         Arg::with_name("color")
+            .long("color")
+            .help("Colors timelines; auto does so only on a terminal")
+            .possible_values(&["auto", "always", "never"])
+            .default_value("auto")
+            .global(true)
+            .takes_value(true),
+      )
+      .arg(
+         Arg::with_name("no-color")
+            .long("no-color")
+            .help("Same as --color never")
+            .global(true),
+      )
+      .subcommand(
+         SubCommand::with_name("init")
+            .arg(
+               Arg::with_name("NAME")
+                  .help("Sets the name of the new timeline")
+                  .required(true)
+                  .index(1),
+            )
+            .arg(
+               Arg::with_name("template")
+                  .long("template")
+                  .help("Starts the timeline from a template, see: templates")
+                  .takes_value(true),
+            )
+            .arg(
+               Arg::with_name("from-stdin")
+                  .long("from-stdin")
+                  .help("Reads the timeline as YAML from stdin")
+                  .conflicts_with("template"),
+            )
+            .arg(
+               Arg::with_name("force")
+                  .long("force")
+                  .help("Replaces a timeline of the same name, with --from-stdin")
+                  .requires("from-stdin"),
+            ),
+      )
+      .subcommand(
+         SubCommand::with_name("add")
+            .arg(
+               Arg::with_name("NAME")
+                  .help("Sets the name of the timeline to modify")
+                  .required(true)
+                  .index(1),
+            )
+            .arg(
+               Arg::with_name("goal")
+                  .short("g")
+                  .long("goal")
+                  .help("Short description of the goal")
+                  .takes_value(true)
+                  .multiple(true)
+                  .number_of_values(1),
+            )
+            .arg(
+               Arg::with_name("note")
+                  .short("n")
+                  .long("note")
+                  .help("Text of a note")
+                  .takes_value(true)
+                  .multiple(true)
+                  .number_of_values(1),
+            )
+            .arg(
+               Arg::with_name("heading")
+                  .long("heading")
+                  .help("Text of a heading")
+                  .takes_value(true)
+                  .multiple(true)
+                  .number_of_values(1),
+            )
+            .arg(
+               Arg::with_name("entry")
+                  .short("e")
+                  .long("entry")
+                  .help("Text of an entry")
+                  .takes_value(true)
+                  .multiple(true)
+                  .number_of_values(1),
+            )
+            .arg(
+               Arg::with_name("timebox")
+                  .short("t")
+                  .long("timebox")
+                  .help("Short description of the timebox")
+                  .takes_value(true)
+                  .multiple(true)
+                  .number_of_values(1),
+            )
+            .arg(
+               Arg::with_name("budget")
+                  .short("b")
+                  .long("budget")
+                  .help("Budget of the timeboxes, like 1h30m [default: from the config, else 1h]")
+                  .takes_value(true),
+            )
+            .arg(
+               Arg::with_name("subtask")
+                  .short("s")
+                  .long("subtask")
+                  .help("Adds a subtask to the goal selected by PARENT")
+                  .value_name("PARENT:TEXT")
+                  .takes_value(true)
+                  .multiple(true)
+                  .number_of_values(1),
+            ),
+      )
+      .subcommand(
+         SubCommand::with_name("now")
+            .arg(
+               Arg::with_name("NAME")
+                  .help("Sets the name of the timeline to display for now")
+                  .index(1),
+            )
+            .arg(
+               Arg::with_name("by")
+                  .long("by")
+                  .help("Groups the items into a view")
+                  .possible_values(&["list", "horizon", "alpha", "geo"])
+                  .default_value("list")
+                  .takes_value(true),
+            )
+            .arg(
+               Arg::with_name("format")
+                  .long("format")
+                  .help("Output format")
+                  .possible_values(&["markdown", "json"])
+                  .default_value("markdown")
+                  .takes_value(true),
+            )
+            .arg(
+               Arg::with_name("tag")
+                  .long("tag")
+                  .help("Shows only the items tagged #TAG")
+                  .value_name("TAG")
+                  .multiple(true)
+                  .number_of_values(1)
+                  .takes_value(true),
+            )
+            .arg(
+               Arg::with_name("person")
+                  .long("person")
+                  .help("Shows only the items mentioning @PERSON")
+                  .value_name("PERSON")
+                  .multiple(true)
+                  .number_of_values(1)
+                  .takes_value(true),
+            )
+            .arg(
+               Arg::with_name("project")
+                  .long("project")
+                  .help("Shows only the items in +PROJECT")
+                  .value_name("PROJECT")
+                  .multiple(true)
+                  .number_of_values(1)
+                  .takes_value(true),
+            )
+            .arg(
+               Arg::with_name("any")
+                  .long("any")
+                  .help("Shows the items passing any of the filters")
+                  .conflicts_with("all"),
+            )
+            .arg(
+               Arg::with_name("all")
+                  .long("all")
+                  .help("Shows the items passing all of the filters [default]"),
+            )
+            .arg(
+               Arg::with_name("sort")
+                  .long("sort")
+                  .help("Orders the items for display without changing the timeline")
+                  .possible_values(&["none", "label", "status", "recent"])
+                  .default_value("none")
+                  .takes_value(true),
+            )
+            .arg(
+               Arg::with_name("limit")
+                  .long("limit")
+                  .value_name("N")
+                  .help("Shows at most N top-level items, after sorting")
+                  .takes_value(true),
+            )
+            .arg(
+               Arg::with_name("offset")
+                  .long("offset")
+                  .value_name("M")
+                  .help("Skips the first M top-level items, after sorting")
+                  .takes_value(true),
+            )
+            .arg(
+               Arg::with_name("watch")
+                  .long("watch")
+                  .help("Shows the timeline again whenever its file changes"),
+            )
+            .arg(
+               Arg::with_name("depth")
+                  .long("depth")
+                  .value_name("N")
+                  .help("Collapses sublists nested deeper than N, 0 shows only top-level items")
+                  .takes_value(true),
+            )
+            .arg(
+               Arg::with_name("width")
+                  .long("width")
+                  .value_name("N")
+                  .help("Wraps notes and entries at N columns, by default the terminal's width")
+                  .takes_value(true),
+            )
+            .arg(
+               Arg::with_name("flat")
+                  .long("flat")
+                  .help("Lifts the items out of sublists, labeled with the sublists they are in"),
+            )
+            .arg(
+               Arg::with_name("collapse-done")
+                  .long("collapse-done")
+                  .help("Hides the done goals and timeboxes, and sublists with nothing else")
+                  .conflicts_with("only-done"),
+            )
+            .arg(
+               Arg::with_name("only-done")
+                  .long("only-done")
+                  .help("Shows only the done goals and timeboxes"),
+            )
+            .arg(
+               Arg::with_name("over-budget")
+                  .long("over-budget")
+                  .help("Shows only the timeboxes that have accrued more than their budget"),
+            )
+            .arg(
+               Arg::with_name("since")
+                  .long("since")
+                  .help("Shows only the items with activity at or after this RFC 3339 time")
+                  .takes_value(true),
+            )
+            .arg(
+               Arg::with_name("until")
+                  .long("until")
+                  .help("Shows only the items with activity at or before this RFC 3339 time")
+                  .takes_value(true),
+            ),
+      )
+      .subcommand(
+         SubCommand::with_name("search")
+            .arg(
+               Arg::with_name("NAME")
+                  .help("Sets the name of the timeline to search")
+                  .required_unless("all")
+                  .index(1),
+            )
+            .arg(
+               Arg::with_name("PATTERN")
+                  .help("Keyword (or regex with --regex) to search for")
+                  .required_unless("all")
+                  .index(2),
+            )
+            .arg(
+               Arg::with_name("all")
+                  .short("a")
+                  .long("all")
+                  .help("Search every timeline in the directory"),
+            )
+            .arg(
+               Arg::with_name("regex")
+                  .short("r")
+                  .long("regex")
+                  .help("Treat PATTERN as a regular expression"),
+            )
+            .arg(
+               Arg::with_name("names")
+                  .long("names")
+                  .conflicts_with("items")
+                  .help("Match against timeline names"),
+            )
+            .arg(
+               Arg::with_name("items")
+                  .long("items")
+                  .help("Match against timeline items (default)"),
+            )
+            .arg(
+               Arg::with_name("show")
+                  .long("show")
+                  .conflicts_with("names")
+                  .help("Prints the matching lines of each item instead of counts"),
+            )
+            .arg(
+               Arg::with_name("open")
+                  .long("open")
+                  .conflicts_with_all(&["done", "names"])
+                  .help("Matches only the goals and timeboxes that are not done"),
+            )
+            .arg(
+               Arg::with_name("done")
+                  .long("done")
+                  .conflicts_with("names")
+                  .help("Matches only the goals and timeboxes that are done"),
+            ),
+      )
+      .subcommand(
+         SubCommand::with_name("start")
+            .arg(
+               Arg::with_name("NAME")
+                  .help("Sets the name of the timeline to modify")
+                  .required(true)
+                  .index(1),
+            )
+            .arg(
+               Arg::with_name("item")
+                  .short("i")
+                  .long("item")
+                  .help("Index (from 1) or label of the timebox to start")
+                  .required(true)
+                  .takes_value(true),
+            ),
+      )
+      .subcommand(
+         SubCommand::with_name("stop")
+            .arg(
+               Arg::with_name("NAME")
+                  .help("Sets the name of the timeline to modify")
+                  .required(true)
+                  .index(1),
+            )
+            .arg(
+               Arg::with_name("item")
+                  .short("i")
+                  .long("item")
+                  .help("Index (from 1) or label of the timebox to stop")
+                  .required(true)
+                  .takes_value(true),
+            ),
+      )
+      .subcommand(
+         SubCommand::with_name("wait")
+            .about("Marks a timebox as blocked until it is started again")
+            .arg(
+               Arg::with_name("NAME")
+                  .help("Sets the name of the timeline to modify")
+                  .required(true)
+                  .index(1),
+            )
+            .arg(
+               Arg::with_name("item")
+                  .short("i")
+                  .long("item")
+                  .help("Index (from 1) or label of the timebox that is blocked")
+                  .required(true)
+                  .takes_value(true),
+            )
+            .arg(
+               Arg::with_name("for")
+                  .long("for")
+                  .help("What the timebox is waiting for")
+                  .required(true)
+                  .takes_value(true),
+            ),
+      )
+      .subcommand(
+         SubCommand::with_name("view")
+            .about("Moves through the history of viewed timelines")
+            .subcommand(
+               SubCommand::with_name("switch").arg(
+                  Arg::with_name("NAME")
+                     .help("Sets the name of the timeline to view")
+                     .required(true)
+                     .index(1),
+               ),
+            )
+            .subcommand(SubCommand::with_name("last").about("Goes back to the previous timeline"))
+            .subcommand(SubCommand::with_name("next").about("Goes forward to the next timeline"))
+            .subcommand(SubCommand::with_name("clear").about("Forgets the view history")),
+      )
+      .subcommand(
+         SubCommand::with_name("import")
+            .about("Appends the tasks of a file to a timeline")
+            .arg(
+               Arg::with_name("FILE")
+                  .help("The file to read")
+                  .required(true)
+                  .index(1),
+            )
+            .arg(
+               Arg::with_name("NAME")
+                  .help("Sets the name of the timeline to import into")
+                  .required(true)
+                  .index(2),
+            )
+            .arg(
+               Arg::with_name("todotxt")
+                  .long("todotxt")
+                  .help("FILE is in todo.txt format"),
+            )
+            .arg(
+               Arg::with_name("markdown")
+                  .long("markdown")
+                  .help("FILE is a markdown task list"),
+            )
+            .group(
+               ArgGroup::with_name("format")
+                  .args(&["todotxt", "markdown"])
+                  .required(true),
+            ),
+      )
+      .subcommand(
+         SubCommand::with_name("export")
+            .about("Writes a timeline to a file")
+            .arg(
+               Arg::with_name("NAME")
+                  .help("Sets the name of the timeline to export")
+                  .required(true)
+                  .index(1),
+            )
+            .arg(
+               Arg::with_name("FILE")
+                  .help("The file to write")
+                  .required(true)
+                  .index(2),
+            )
+            .arg(
+               Arg::with_name("todotxt")
+                  .long("todotxt")
+                  .help("Write FILE in todo.txt format"),
+            )
+            .arg(
+               Arg::with_name("markdown")
+                  .long("markdown")
+                  .help("Write FILE as GitHub-flavored markdown"),
+            )
+            .group(
+               ArgGroup::with_name("format")
+                  .args(&["todotxt", "markdown"])
+                  .required(true),
+            ),
+      )
+      .subcommand(
+         SubCommand::with_name("list")
+            .about("Lists the timelines in the directory")
+            .arg(
+               Arg::with_name("json")
+                  .long("json")
+                  .help("Prints the list as a JSON array"),
+            )
+            .arg(
+               Arg::with_name("sort")
+                  .long("sort")
+                  .help("Orders by name, or by the latest modified first")
+                  .possible_values(&["name", "recent"])
+                  .default_value("name")
+                  .takes_value(true),
+            )
+            .arg(
+               Arg::with_name("limit")
+                  .long("limit")
+                  .value_name("N")
+                  .help("Shows at most N timelines")
+                  .takes_value(true),
+            )
+            .arg(
+               Arg::with_name("offset")
+                  .long("offset")
+                  .value_name("M")
+                  .help("Skips the first M timelines")
+                  .takes_value(true),
+            ),
+      )
+      .subcommand(
+         SubCommand::with_name("done")
+            .arg(
+               Arg::with_name("NAME")
+                  .help("Sets the name of the timeline to modify")
+                  .required(true)
+                  .index(1),
+            )
+            .arg(
+               Arg::with_name("item")
+                  .short("i")
+                  .long("item")
+                  .help("Index (from 1) or label of the item to mark done")
+                  .takes_value(true),
+            )
+            .arg(
+               Arg::with_name("all-matching")
+                  .long("all-matching")
+                  .value_name("PATTERN")
+                  .help("Marks every open goal and timebox whose label matches PATTERN done")
+                  .takes_value(true),
+            )
+            .arg(
+               Arg::with_name("regex")
+                  .long("regex")
+                  .help("Reads PATTERN as a regular expression")
+                  .requires("all-matching"),
+            )
+            .group(
+               ArgGroup::with_name("which")
+                  .args(&["item", "all-matching"])
+                  .required(true),
+            ),
+      )
+      .subcommand(
+         SubCommand::with_name("undone")
+            .arg(
+               Arg::with_name("NAME")
+                  .help("Sets the name of the timeline to modify")
+                  .required(true)
+                  .index(1),
+            )
+            .arg(
+               Arg::with_name("item")
+                  .short("i")
+                  .long("item")
+                  .help("Index (from 1) or label of the item to mark not done")
+                  .required(true)
+                  .takes_value(true),
+            ),
+      )
+      .subcommand(
+         SubCommand::with_name("remove")
+            .arg(
+               Arg::with_name("NAME")
+                  .help("Sets the name of the timeline to modify")
+                  .required(true)
+                  .index(1),
+            )
+            .arg(
+               Arg::with_name("item")
+                  .short("i")
+                  .long("item")
+                  .help("Index (from 1) or label of an item to remove")
+                  .required(true)
+                  .takes_value(true)
+                  .multiple(true)
+                  .number_of_values(1),
+            ),
+      )
+      .subcommand(
+         SubCommand::with_name("tags")
+            .about("Lists the tags used in a timeline")
+            .arg(
+               Arg::with_name("NAME")
+                  .help("Sets the name of the timeline to scan")
+                  .required(true)
+                  .index(1),
+            )
+            .arg(
+               Arg::with_name("cloud")
+                  .long("cloud")
+                  .help("Prints just the tags, sorted, on one line"),
+            ),
+      )
+      .subcommand(
+         SubCommand::with_name("people")
+            .about("Lists the people mentioned in a timeline")
+            .arg(
+               Arg::with_name("NAME")
+                  .help("Sets the name of the timeline to scan")
+                  .required(true)
+                  .index(1),
+            ),
+      )
+      .subcommand(
+         SubCommand::with_name("tui")
+            .about("Opens a timeline full screen to move through and edit")
+            .arg(
+               Arg::with_name("NAME")
+                  .help("Sets the name of the timeline to open")
+                  .required(true)
+                  .index(1),
+            ),
+      )
+      .subcommand(
+         SubCommand::with_name("stats")
+            .about("Summarizes what is in a timeline")
+            .arg(
+               Arg::with_name("NAME")
+                  .help("Sets the name of the timeline to summarize")
+                  .required(true)
+                  .index(1),
+            )
+            .arg(
+               Arg::with_name("json")
+                  .long("json")
+                  .help("Prints the summary as JSON"),
+            ),
+      )
+      .subcommand(
+         SubCommand::with_name("schedule")
+            .about("Schedules a goal or a timebox")
+            .arg(
+               Arg::with_name("NAME")
+                  .help("Sets the name of the timeline to modify")
+                  .required(true)
+                  .index(1),
+            )
+            .arg(
+               Arg::with_name("item")
+                  .short("i")
+                  .long("item")
+                  .help("Index (from 1) or label of the item to schedule")
+                  .required(true)
+                  .takes_value(true),
+            )
+            .arg(
+               Arg::with_name("begins")
+                  .long("begins")
+                  .help("When it begins, in RFC 3339")
+                  .required_unless("ends")
+                  .takes_value(true),
+            )
+            .arg(
+               Arg::with_name("ends")
+                  .long("ends")
+                  .help("When it ends, in RFC 3339")
+                  .takes_value(true),
+            ),
+      )
+      .subcommand(
+         SubCommand::with_name("undo")
+            .about("Takes back the last change to a timeline")
+            .arg(
+               Arg::with_name("NAME")
+                  .help("Sets the name of the timeline to restore")
+                  .required(true)
+                  .index(1),
+            )
+            .arg(
+               Arg::with_name("list")
+                  .long("list")
+                  .help("Lists the changes that can be taken back, latest first"),
+            ),
+      )
+      .subcommand(
+         SubCommand::with_name("move")
+            .about("Moves an item to another position in its timeline")
+            .arg(
+               Arg::with_name("NAME")
+                  .help("Sets the name of the timeline to modify")
+                  .required(true)
+                  .index(1),
+            )
+            .arg(
+               Arg::with_name("item")
+                  .short("i")
+                  .long("item")
+                  .help("Index (from 1) or label of the item to move")
+                  .required(true)
+                  .takes_value(true),
+            )
+            .arg(
+               Arg::with_name("to")
+                  .long("to")
+                  .help("The position (from 1) the item ends up at")
+                  .takes_value(true),
+            )
+            .arg(
+               Arg::with_name("before")
+                  .long("before")
+                  .help("Index (from 1) or label of the item to move it before")
+                  .takes_value(true),
+            )
+            .arg(
+               Arg::with_name("after")
+                  .long("after")
+                  .help("Index (from 1) or label of the item to move it after")
+                  .takes_value(true),
+            )
+            .arg(
+               Arg::with_name("to-context")
+                  .long("to-context")
+                  .help("The name of the timeline to append the item to")
+                  .takes_value(true),
+            )
+            .arg(
+               Arg::with_name("create")
+                  .long("create")
+                  .requires("to-context")
+                  .help("Creates the timeline to move to if it does not exist"),
+            )
+            .group(
+               ArgGroup::with_name("target")
+                  .args(&["to", "before", "after", "to-context"])
+                  .required(true),
+            ),
+      )
+      .subcommand(
+         SubCommand::with_name("edit")
+            .about("Opens a timeline in $EDITOR and checks it afterwards")
+            .arg(
+               Arg::with_name("NAME")
+                  .help("Sets the name of the timeline to edit")
+                  .required(true)
+                  .index(1),
+            ),
+      )
+      .subcommand(
+         SubCommand::with_name("validate")
+            .about("Checks that timelines load and are consistent")
+            .arg(
+               Arg::with_name("NAME")
+                  .help("Sets the name of the timeline to check")
+                  .required_unless("all")
+                  .index(1),
+            )
+            .arg(
+               Arg::with_name("all")
+                  .long("all")
+                  .help("Checks every timeline in the directory"),
+            ),
+      )
+      .subcommand(
+         SubCommand::with_name("roll")
+            .about("Reopens the done recurring items and schedules them again")
+            .arg(
+               Arg::with_name("NAME")
+                  .help("Sets the name of the timeline to roll over")
+                  .required(true)
+                  .index(1),
+            ),
+      )
+      .subcommand(
+         SubCommand::with_name("completions")
+            .about("Prints a shell completion script to stdout")
+            .arg(
+               Arg::with_name("SHELL")
+                  .help("Sets the shell to complete for")
+                  .possible_values(&["bash", "zsh", "fish", "powershell"])
+                  .required(true)
+                  .index(1),
+            ),
+      )
+      .subcommand(
+         SubCommand::with_name("diff")
+            .about("Shows accrued times that differ from what the history adds up to")
+            .arg(
+               Arg::with_name("NAME")
+                  .help("Sets the name of the timeline to compare")
+                  .required(true)
+                  .index(1),
+            ),
+      )
+      .subcommand(
+         SubCommand::with_name("fix")
+            .about("Recomputes accrued times from the history and saves them")
+            .arg(
+               Arg::with_name("NAME")
+                  .help("Sets the name of the timeline to fix")
+                  .required(true)
+                  .index(1),
+            ),
+      )
+      .subcommand(
+         SubCommand::with_name("up")
+            .about("Swaps an item with the one above it")
+            .arg(
+               Arg::with_name("NAME")
+                  .help("Sets the name of the timeline to modify")
+                  .required(true)
+                  .index(1),
+            )
+            .arg(
+               Arg::with_name("item")
+                  .short("i")
+                  .long("item")
+                  .help("Index (from 1) or label of the item to move up")
+                  .required(true)
+                  .takes_value(true),
+            ),
+      )
+      .subcommand(
+         SubCommand::with_name("down")
+            .about("Swaps an item with the one below it")
+            .arg(
+               Arg::with_name("NAME")
+                  .help("Sets the name of the timeline to modify")
+                  .required(true)
+                  .index(1),
+            )
+            .arg(
+               Arg::with_name("item")
+                  .short("i")
+                  .long("item")
+                  .help("Index (from 1) or label of the item to move down")
+                  .required(true)
+                  .takes_value(true),
+            ),
+      )
+      .subcommand(
+         SubCommand::with_name("archive")
+            .about("Moves done goals and finished timeboxes to NAME.archive")
+            .arg(
+               Arg::with_name("NAME")
+                  .help("Sets the name of the timeline to archive from")
+                  .required(true)
+                  .index(1),
+            )
+            .arg(
+               Arg::with_name("before")
+                  .long("before")
+                  .value_name("DATE")
+                  .help("Archives only the items finished before DATE, like 2021-03-01")
+                  .takes_value(true),
+            ),
+      )
+      .subcommand(
+         SubCommand::with_name("graph")
+            .about("Prints how the timelines link to each other")
+            .arg(
+               Arg::with_name("format")
+                  .long("format")
+                  .help("Prints a Graphviz digraph or JSON")
+                  .possible_values(&["dot", "json"])
+                  .default_value("dot")
+                  .takes_value(true),
+            ),
+      )
+      .subcommand(
+         SubCommand::with_name("tree")
+            .about("Shows a timeline as a tree of its items")
+            .arg(
+               Arg::with_name("NAME")
+                  .help("Sets the name of the timeline to show")
+                  .required(true)
+                  .index(1),
+            ),
+      )
+      .subcommand(
+         SubCommand::with_name("cat")
+            .about("Prints the YAML file of a timeline")
+            .arg(
+               Arg::with_name("NAME")
+                  .help("Sets the name of the timeline to print")
+                  .required(true)
+                  .index(1),
+            )
+            .arg(
+               Arg::with_name("pretty")
+                  .long("pretty")
+                  .help("Prints the timeline as it would be saved rather than the file as it is"),
+            )
+            .arg(
+               Arg::with_name("highlight")
+                  .long("highlight")
+                  .help("Colors the keys and values"),
+            ),
+      )
+      .subcommand(
+         SubCommand::with_name("rename")
+            .about("Gives a timeline a new name")
+            .arg(
+               Arg::with_name("NAME")
+                  .help("Sets the name of the timeline to rename")
+                  .required(true)
+                  .index(1),
+            )
+            .arg(
+               Arg::with_name("NEWNAME")
+                  .help("The name to give it")
+                  .required(true)
+                  .index(2),
+            )
+            .arg(
+               Arg::with_name("force")
+                  .long("force")
+                  .help("Replaces a timeline already called NEWNAME"),
+            ),
+      )
+      .subcommand(
+         SubCommand::with_name("clone")
+            .about("Copies a timeline to a new name")
+            .arg(
+               Arg::with_name("NAME")
+                  .help("Sets the name of the timeline to copy")
+                  .required(true)
+                  .index(1),
+            )
+            .arg(
+               Arg::with_name("NEWNAME")
+                  .help("The name of the copy")
+                  .required(true)
+                  .index(2),
+            )
+            .arg(
+               Arg::with_name("fresh")
+                  .long("fresh")
+                  .help("Opens the goals and clears the time spent in the copy"),
+            ),
+      )
+      .subcommand(
+         SubCommand::with_name("templates").about("Lists the templates init can start from"),
+      )
+      .subcommand(
+         SubCommand::with_name("report")
+            .about("Totals the time spent in every timeline over a period")
+            .arg(
+               Arg::with_name("day")
+                  .long("day")
+                  .help("Reports on today"),
+            )
+            .arg(
+               Arg::with_name("week")
+                  .long("week")
+                  .help("Reports on this week, from Monday (the default)"),
+            )
+            .arg(
+               Arg::with_name("month")
+                  .long("month")
+                  .help("Reports on this month"),
+            )
+            .group(ArgGroup::with_name("period").args(&["day", "week", "month"]))
+            .arg(
+               Arg::with_name("json")
+                  .long("json")
+                  .help("Prints the report as JSON"),
+            ),
+      )
+      .subcommand(
+         SubCommand::with_name("budget")
+            .about("Changes the budget of a timebox")
+            .arg(
+               Arg::with_name("NAME")
+                  .help("Sets the name of the timeline to modify")
+                  .required(true)
+                  .index(1),
+            )
+            .arg(
+               Arg::with_name("item")
+                  .short("i")
+                  .long("item")
+                  .help("Index (from 1) or label of the timebox to budget")
+                  .required(true)
+                  .takes_value(true),
+            )
+            .arg(
+               Arg::with_name("set")
+                  .long("set")
+                  .help("Sets the budget to a duration like 2h")
+                  .takes_value(true),
+            )
+            .arg(
+               Arg::with_name("add")
+                  .long("add")
+                  .help("Adds a duration like 30m to the budget")
+                  .takes_value(true),
+            )
+            .arg(
+               Arg::with_name("sub")
+                  .long("sub")
+                  .help("Takes a duration like 15m off the budget, down to nothing")
+                  .takes_value(true),
+            )
+            .group(
+               ArgGroup::with_name("change")
+                  .args(&["set", "add", "sub"])
+                  .required(true),
+            ),
+      )
+}
+
+/// Parses `argv`, program name first, and runs the subcommand it names.
+fn run<I, T>(argv: I) -> Result<(), INNError>
+where
+   I: IntoIterator<Item = T>,
+   T: Into<std::ffi::OsString> + Clone,
+{
+   let matches = build_cli().get_matches_from(argv);
+   let config = config::Config::load()?;
+   if let Some(dir) = matches.value_of("dir").or(config.dir.as_deref()) {
+      STORE_DIR.get_or_init(|| PathBuf::from(dir));
+   }
+   dryrun::DRY_RUN.get_or_init(|| matches.is_present("dry-run"));
+   verbosity::VERBOSITY.get_or_init(|| {
+      if matches.is_present("quiet") {
+         verbosity::Verbosity::Quiet
+      } else if matches.is_present("verbose") {
+         verbosity::Verbosity::Verbose
+      } else {
+         verbosity::Verbosity::Normal
+      }
+   });
+   COLOR.get_or_init(|| match matches.value_of("color") {
+      _ if matches.is_present("no-color") => false,
+      Some("always") => true,
+      Some("never") => false,
+      _ => std::io::stdout().is_terminal(),
+   });
+   let mut cmd_queue: std::collections::VecDeque<Cmd> = vec![subcommand_cmd(&matches)].into();
+   let empty = ArgMatches::default();
+   let args = matches.subcommand().1.unwrap_or(&empty);
+   while let Some(cmd) = cmd_queue.pop_front() {
+      cmd_queue.extend(run_cmd(&cmd, args, &config)?);
+   }
+   Ok(())
+}
+
+/// A subcommand's handler, given its arguments.
+type Handler = fn(&ArgMatches, &config::Config) -> Result<(), INNError>;
+
+/// The subcommands with a handler of their own, queued as
+/// `Cmd::Handler` with the name here. `init --from-stdin` has its own
+/// entry since it reads the timeline instead of starting one.
+const HANDLERS: &[(&str, Handler)] = &[
+   ("init --from-stdin", |args, _| run_init_stdin(args)),
+   ("add", run_add),
+   ("now", |args, _| run_now(args)),
+   ("search", |args, _| run_search(args)),
+   ("start", |args, _| run_start(args)),
+   ("stop", |args, _| run_stop(args)),
+   ("view", |args, _| run_view(args)),
+   ("import", |args, _| run_import(args)),
+   ("export", |args, _| run_export(args)),
+   ("list", |args, _| run_list(args)),
+   ("done", |args, _| run_done(args, true)),
+   ("undone", |args, _| run_done(args, false)),
+   ("remove", |args, _| run_remove(args)),
+   ("tags", |args, _| run_tags(args)),
+   ("people", |args, _| run_people(args)),
+   ("tui", |args, _| run_tui(args)),
+   ("wait", |args, _| run_wait(args)),
+   ("stats", |args, _| run_stats(args)),
+   ("schedule", |args, _| run_schedule(args)),
+   ("undo", |args, _| run_undo(args)),
+   ("move", |args, _| run_move(args)),
+   ("edit", run_edit),
+   ("validate", |args, _| run_validate(args)),
+   ("roll", |args, _| run_roll(args)),
+   ("completions", |args, _| run_completions(args)),
+   ("diff", |args, _| run_diff(args)),
+   ("fix", |args, _| run_fix(args)),
+   ("up", |args, _| run_step(args, true)),
+   ("down", |args, _| run_step(args, false)),
+   ("archive", |args, _| run_archive(args)),
+   ("graph", |args, _| run_graph(args)),
+   ("tree", |args, _| run_tree(args)),
+   ("cat", |args, _| run_cat(args)),
+   ("rename", |args, _| run_rename(args)),
+   ("clone", |args, _| run_clone(args)),
+   ("templates", |args, _| run_templates(args)),
+   ("report", |args, _| run_report(args)),
+   ("budget", |args, _| run_budget(args)),
+];
+
+/// The command the subcommand in `matches` is queued as.
+fn subcommand_cmd(matches: &ArgMatches) -> Cmd {
+   match matches.subcommand() {
+      ("init", Some(args)) if args.is_present("from-stdin") => Cmd::Handler("init --from-stdin"),
+      ("init", Some(args)) => {
+         let name = args.value_of("NAME").unwrap();
+         let template = args.value_of("template").map(|t| t.to_string());
+         contexts::Cmd::Init(name.to_string(), template).into()
+      }
+      ("help", Some(_args)) => Cmd::Help,
+      ("view", Some(args)) => match args.subcommand() {
+         ("switch", Some(args)) => {
+            contexts::Cmd::Switch(args.value_of("NAME").unwrap().to_string()).into()
+         }
+         ("last", _) => contexts::Cmd::Last.into(),
+         ("next", _) => contexts::Cmd::Next.into(),
+         ("clear", _) => contexts::Cmd::Clear.into(),
+         _ => Cmd::Handler("view"),
+      },
+      (name, Some(_)) => match HANDLERS.iter().find(|(handled, _)| *handled == name) {
+         Some((handled, _)) => Cmd::Handler(handled),
+         None => Cmd::Noop,
+      },
+      _ => Cmd::Noop,
+   }
+}
+
+/// Runs a queued command with the subcommand's arguments, and returns
+/// what it queues next.
+fn run_cmd(cmd: &Cmd, args: &ArgMatches, config: &config::Config) -> Result<Option<Cmd>, INNError> {
+   match cmd {
+      Cmd::Noop => {}
+      Cmd::Help => {
+         build_cli().print_help().map_err(|e| INNError::File(std::io::Error::other(e.message)))?;
+         println!();
+      }
+      Cmd::Context(cxc) => {
+         let mut model = Model::load()?;
+         contexts::run(cxc, &mut model)?;
+         if cxc.as_view().is_some() {
+            return Ok(model.view_history.current().map(|name| Cmd::Show(name.to_string())));
+         }
+      }
+      Cmd::Handler(name) => {
+         let (_, handler) = HANDLERS.iter().find(|(handled, _)| handled == name).unwrap();
+         handler(args, config)?
+      }
+      Cmd::Show(name) => show_timeline(name)?,
+   }
+   Ok(None)
+}
+
+fn run_templates(_matches: &ArgMatches) -> Result<(), INNError> {
+   for t in template::TEMPLATES.iter() {
+      println!("{}: {}", t.name, t.about);
+   }
+   Ok(())
+}
+
+fn write_atomically(filename: &str, contents: &str) -> Result<(), INNError> {
+   if dryrun::dry_run() {
+      info!("dry run: not writing {}", filename);
+      return Ok(());
+   }
+   let tmp_filename = format!("{}.tmp", filename);
+   let mut buf = File::create(&tmp_filename)?;
+   buf.write_all(contents.as_bytes())?;
+   buf.sync_all()?;
+   std::fs::rename(&tmp_filename, filename)?;
+   Ok(())
+}
+
+/// Checks the YAML through `migrate` like any load, so an old or broken
+/// timeline is upgraded or refused before anything is written.
+fn run_init_stdin(matches: &ArgMatches) -> Result<(), INNError> {
+   let name = matches.value_of("NAME").unwrap();
+   check_name(name)?;
+   let filename = ListV1::filename(name);
+   if Path::new(&filename).exists() && !matches.is_present("force") {
+      return Err(INNError::AlreadyExists(filename));
+   }
+   let mut text = String::new();
+   std::io::stdin().read_to_string(&mut text)?;
+   let timeline = ListV1 {
+      name: name.to_string(),
+      ..migrate::migrate(&text, "stdin")?
+   };
+   timeline.save()
+}
+
+/// Evens out what differs between serde_yaml versions so that saved
+/// timelines diff cleanly: no `---` document marker, indentation in
+/// steps of two spaces, no trailing whitespace and one newline at the
+/// end. serde_yaml quotes any string that would lose something here.
+fn normalize_yaml(yaml: &str) -> String {
+   let lines: Vec<&str> = yaml
+      .lines()
+      .map(|line| line.trim_end())
+      .skip_while(|line| *line == "---" || line.is_empty())
+      .collect();
+   let indent = |line: &str| line.len() - line.trim_start_matches(' ').len();
+   let step = lines
+      .iter()
+      .map(|line| indent(line))
+      .filter(|n| *n > 0)
+      .min()
+      .unwrap_or(2);
+   // Rescale only when every indent is a whole number of steps.
+   let rescale = step != 2 && lines.iter().all(|line| indent(line) % step == 0);
+   let mut out = String::from("");
+   for line in lines.iter() {
+      if rescale {
+         out.push_str(&" ".repeat(indent(line) / step * 2));
+         out.push_str(line.trim_start_matches(' '));
+      } else {
+         out.push_str(line);
+      }
+      out.push('\n');
+   }
+   while out.ends_with("\n\n") {
+      out.pop();
+   }
+   out
+}
+
+/// `init starter` is short for `init starter --template starter`.
+fn init_timeline(name: &str, template: Option<&str>) -> Result<(), INNError> {
+   check_name(name)?;
+   let template = template.or((name == "starter").then_some("starter"));
+   let timeline = match template {
+      Some(template) => template::timeline(template, name)?,
+      None => ListV1::new(name),
+   };
+   let filename = ListV1::filename(name);
+
+   if std::path::Path::new(&filename).exists() {
+      error!("{} exists, not overwriting", filename);
+      Ok(())
+   } else {
+      timeline.save()
+   }
+}
+
+/// `ListV1::find_item` with the error told in terms of the labels.
+fn select_item(list: &ListV1, selector: &str) -> Result<usize, INNError> {
+   let found = list.find_item(selector);
+   if let Ok(idx) = found {
+      debug!("{:?} selects item {}: {:?}", selector, idx + 1, list.items[idx].label());
+   }
+   found.map_err(|e| match e {
+      SelectError::NotFound => INNError::NoSuchItem(selector.to_string()),
+      SelectError::Ambiguous(candidates) => INNError::AmbiguousItem(
+         selector.to_string(),
+         candidates
+            .iter()
+            .map(|idx| format!("{}: {}", idx + 1, list.items[*idx].label()))
+            .collect(),
+      ),
+   })
+}
+
+fn select_timebox<'a>(
+   list: &'a mut ListV1,
+   selector: &str,
+) -> Result<&'a mut CheckTimebox, INNError> {
+   let idx = select_item(list, selector)?;
+   match &mut list.items[idx] {
+      ListItem::Timebox(tb) => Ok(tb),
+      item => Err(INNError::NotATimebox(item.label().to_string())),
+   }
+}
+
+fn run_start(matches: &ArgMatches) -> Result<(), INNError> {
+   let name = matches.value_of("NAME").unwrap();
+   let mut timeline = ListV1::load(name)?;
+   let tb = select_timebox(&mut timeline, matches.value_of("item").unwrap())?;
+   if tb.is_running() {
+      warn!("{:?} is already started", tb.label);
+      return Ok(());
+   }
+   tb.history.push(AttentionEvent::Started(Utc::now()));
+   tb.accrued = tb.recompute_accrued();
+   timeline.save()
+}
+
+fn run_budget(matches: &ArgMatches) -> Result<(), INNError> {
+   let name = matches.value_of("NAME").unwrap();
+   let mut timeline = ListV1::load(name)?;
+   let tb = select_timebox(&mut timeline, matches.value_of("item").unwrap())?;
+   let old = tb.budget.clone();
+   tb.budget = match (matches.value_of("set"), matches.value_of("add"), matches.value_of("sub")) {
+      (Some(set), _, _) => Timespan::from_human(set)?,
+      (_, Some(add), _) => old.add(&Timespan::from_human(add)?),
+      (_, _, Some(sub)) => old.saturating_sub(&Timespan::from_human(sub)?),
+      _ => old.clone(),
+   };
+   info!("{:?}: budget {} -> {}", tb.label, old, tb.budget);
+   timeline.save()
+}
+
+fn run_stop(matches: &ArgMatches) -> Result<(), INNError> {
+   let name = matches.value_of("NAME").unwrap();
+   let mut timeline = ListV1::load(name)?;
+   let tb = select_timebox(&mut timeline, matches.value_of("item").unwrap())?;
+   if !tb.is_running() {
+      return Err(INNError::NotRunning(tb.label.clone()));
+   }
+   tb.history.push(AttentionEvent::Paused(Utc::now()));
+   tb.accrued = tb.recompute_accrued();
+   timeline.save()
+}
+
+/// Waiting ends a running interval the same way a pause does.
+fn run_wait(matches: &ArgMatches) -> Result<(), INNError> {
+   let name = matches.value_of("NAME").unwrap();
+   let mut timeline = ListV1::load(name)?;
+   let tb = select_timebox(&mut timeline, matches.value_of("item").unwrap())?;
+   let reason = matches.value_of("for").unwrap().to_string();
+   tb.history
+      .push(AttentionEvent::WaitingFor(Utc::now(), reason));
+   tb.accrued = tb.recompute_accrued();
+   timeline.save()
+}
+
+/// Parses an RFC 3339 time given to an option, if it was given.
+fn time_arg(matches: &ArgMatches, name: &str) -> Result<Option<DTUtc>, INNError> {
+   match matches.value_of(name) {
+      None => Ok(None),
+      Some(s) => DateTime::parse_from_rfc3339(s)
+         .map(|ts| Some(ts.with_timezone(&Utc)))
+         .map_err(|_| INNError::BadTime(s.to_string())),
+   }
+}
+
+/// Like `time_arg`, but a plain date like `2021-03-01` is taken as the
+/// start of that day in UTC.
+fn date_arg(matches: &ArgMatches, name: &str) -> Result<Option<DTUtc>, INNError> {
+   match matches.value_of(name) {
+      Some(s) => match NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+         Ok(date) => Ok(Some(Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap()))),
+         Err(_) => time_arg(matches, name),
+      },
+      None => Ok(None),
+   }
+}
+
+/// Parses a whole number given to an option, if it was given.
+fn count_arg(matches: &ArgMatches, name: &str) -> Result<Option<usize>, INNError> {
+   match matches.value_of(name) {
+      None => Ok(None),
+      Some(s) => s
+         .parse::<usize>()
+         .map(Some)
+         .map_err(|_| INNError::BadCount(s.to_string())),
+   }
+}
+
+/// Keeps the part of `items` picked by `--offset` and `--limit`. When
+/// either is given a footer like `showing 21-40 of 153` says which part.
+fn page<T>(items: Vec<T>, matches: &ArgMatches) -> Result<(Vec<T>, Option<String>), INNError> {
+   let (offset, limit) = (count_arg(matches, "offset")?, count_arg(matches, "limit")?);
+   if offset.is_none() && limit.is_none() {
+      return Ok((items, None));
+   }
+   let total = items.len();
+   let page: Vec<T> = items
+      .into_iter()
+      .skip(offset.unwrap_or(0))
+      .take(limit.unwrap_or(usize::MAX))
+      .collect();
+   let footer = match page.len() {
+      0 => format!("showing none of {}", total),
+      n => {
+         let first = offset.unwrap_or(0) + 1;
+         format!("showing {}-{} of {}", first, first + n - 1, total)
+      }
+   };
+   Ok((page, Some(footer)))
+}
+
+/// Replaces any schedule the item already had.
+fn run_schedule(matches: &ArgMatches) -> Result<(), INNError> {
+   let name = matches.value_of("NAME").unwrap();
+   let mut timeline = ListV1::load(name)?;
+   let idx = select_item(&timeline, matches.value_of("item").unwrap())?;
+   let item = &mut timeline.items[idx];
+   let event = Event::scheduled(
+      ListV1::new(item.label()),
+      time_arg(matches, "begins")?,
+      time_arg(matches, "ends")?,
+   )?;
+   match item {
+      ListItem::Goal(goal) => goal.scheduled = Some(event),
+      ListItem::Timebox(tb) => tb.scheduled = Some(event),
+      item => return Err(INNError::NotSchedulable(item.label().to_string())),
+   }
+   timeline.save()
+}
+
+/// Marks a goal or a timebox done, or not done.
+fn run_done(matches: &ArgMatches, done: bool) -> Result<(), INNError> {
+   let name = matches.value_of("NAME").unwrap();
+   let mut timeline = ListV1::load(name)?;
+   if let Some(text) = matches.value_of("all-matching") {
+      return done_all_matching(matches, timeline, text);
+   }
+   let idx = select_item(&timeline, matches.value_of("item").unwrap())?;
+   mark_done(&mut timeline.items[idx], done, Utc::now())?;
+   timeline.save()
+}
+
+fn mark_done(item: &mut ListItem, done: bool, now: DTUtc) -> Result<(), INNError> {
+   match item {
+      ListItem::Goal(goal) => goal.set_done(done, now),
+      ListItem::Timebox(tb) if done => {
+         tb.done = Some(now);
+         tb.history.push(AttentionEvent::Finished(now));
+         tb.accrued = tb.recompute_accrued();
+      }
+      ListItem::Timebox(tb) => tb.done = None,
+      item => return Err(INNError::NotAGoal(item.label().to_string())),
+   }
+   Ok(())
+}
+
+/// Marks done the open goals and timeboxes with a match in their label.
+/// An empty pattern would match them all and is refused.
+fn done_all_matching(
+   matches: &ArgMatches,
+   mut timeline: ListV1,
+   text: &str,
+) -> Result<(), INNError> {
+   if text.trim().is_empty() {
+      return Err(INNError::EmptyPattern);
+   }
+   let pattern = if matches.is_present("regex") {
+      Pattern::Regex(text.to_string())
+   } else {
+      Pattern::Keyword(text.to_string())
+   };
+   if let Some(e) = pattern.check_errors() {
+      return Err(INNError::Pattern(e));
+   }
+   let now = Utc::now();
+   let mut marked = 0;
+   for item in timeline.items.iter_mut() {
+      let spans = item.match_spans(&pattern).map_err(INNError::Pattern)?;
+      if item.is_done() != Some(false) || spans.is_empty() {
+         continue;
+      }
+      debug!("marking {:?} done", item.label());
+      mark_done(item, true, now)?;
+      marked += 1;
+   }
+   if marked == 0 {
+      info!("nothing open matches {:?}", text);
+      return Ok(());
+   }
+   timeline.save()?;
+   if dryrun::dry_run() {
+      info!("would mark {} item(s) done", marked);
+   } else {
+      info!("marked {} item(s) done", marked);
+   }
+   Ok(())
+}
+
+/// Selects every item before removing any, so the indices given refer
+/// to the timeline as it was.
+fn run_remove(matches: &ArgMatches) -> Result<(), INNError> {
+   let name = matches.value_of("NAME").unwrap();
+   let mut timeline = ListV1::load(name)?;
+   let mut selected = vec![];
+   for selector in matches.values_of("item").unwrap() {
+      selected.push(select_item(&timeline, selector)?);
+   }
+   selected.sort_unstable();
+   selected.dedup();
+   // The links in the removed items are resolved while they are in it.
+   let links = LinkTargets::resolve(&timeline);
+   let mut removed = vec![];
+   for idx in selected.iter().rev() {
+      removed.push((idx + 1, timeline.items.remove(*idx)));
+   }
+   timeline.save()?;
+   for (n, item) in removed.iter().rev() {
+      info!("removed {}: {}", n, render_item(item, "", Utc::now(), &links).trim_end());
+   }
+   Ok(())
+}
+
+/// Other items shift to make room, and `--before` and `--after` refer
+/// to where the other item is before the move. Moving to the timeline
+/// the item is already in moves it to the end.
+fn run_move(matches: &ArgMatches) -> Result<(), INNError> {
+   let name = matches.value_of("NAME").unwrap();
+   let mut timeline = ListV1::load(name)?;
+   let len = timeline.items.len();
+   let idx = select_item(&timeline, matches.value_of("item").unwrap())?;
+   if let Some(other) = matches
+      .value_of("to-context")
+      .filter(|other| *other != name)
+   {
+      return move_to_context(timeline, idx, other, matches.is_present("create"));
+   }
+   let to = if matches.is_present("to-context") {
+      len - 1
+   } else if let Some(pos) = matches.value_of("to") {
+      match pos.parse::<usize>() {
+         Ok(n) if n >= 1 && n <= len => n - 1,
+         _ => return Err(INNError::NoSuchPosition(pos.to_string(), len)),
+      }
+   } else {
+      let (selector, offset) = match matches.value_of("before") {
+         Some(selector) => (selector, 0),
+         None => (matches.value_of("after").unwrap(), 1),
+      };
+      let other = select_item(&timeline, selector)?;
+      if other == idx {
+         return Ok(());
+      }
+      // Taking the item out shifts everything after it up by one.
+      let other = if other > idx { other - 1 } else { other };
+      other + offset
+   };
+   let item = timeline.items.remove(idx);
+   timeline.items.insert(to, item);
+   timeline.save()
+}
+
+fn swap_items(items: &mut [ListItem], i: usize, j: usize) {
+   items.swap(i, j);
+}
+
+/// Swaps the item with the one above it, or below it when `up` is
+/// false. An item already at that end stays where it is.
+fn run_step(matches: &ArgMatches, up: bool) -> Result<(), INNError> {
+   let mut timeline = ListV1::load(matches.value_of("NAME").unwrap())?;
+   let idx = select_item(&timeline, matches.value_of("item").unwrap())?;
+   let other = if up {
+      idx.checked_sub(1)
+   } else {
+      Some(idx + 1).filter(|other| *other < timeline.items.len())
+   };
+   match other {
+      Some(other) => {
+         swap_items(&mut timeline.items, idx, other);
+         timeline.save()
+      }
+      None => {
+         info!(
+            "{:?} is already at the {}",
+            timeline.items[idx].label(),
+            if up { "top" } else { "bottom" }
+         );
+         Ok(())
+      }
+   }
+}
+
+/// Appends the item to the other timeline. That timeline is saved
+/// first, so a failure in between leaves the item in both rather than
+/// in neither.
+fn move_to_context(
+   mut timeline: ListV1,
+   idx: usize,
+   other: &str,
+   create: bool,
+) -> Result<(), INNError> {
+   check_name(other)?;
+   let mut target = match ListV1::load(other) {
+      Err(INNError::NotFound(_)) if create => ListV1::new(other),
+      loaded => loaded?,
+   };
+   target.items.push(timeline.items.remove(idx));
+   target.save()?;
+   timeline.save()
+}
+
+/// Moves the done goals and finished timeboxes to `NAME.archive`, in
+/// the order they were in. Items in sublists are left where they are.
+/// The archive is saved first, so a failure in between leaves the items
+/// in both.
+fn run_archive(matches: &ArgMatches) -> Result<(), INNError> {
+   let name = matches.value_of("NAME").unwrap();
+   let mut timeline = ListV1::load(name)?;
+   let before = date_arg(matches, "before")?;
+   let archive_name = format!("{}.archive", name);
+   let mut archive = match ListV1::load(&archive_name) {
+      Err(INNError::NotFound(_)) => ListV1::new(&archive_name),
+      loaded => loaded?,
+   };
+   // Without a cutoff every done item goes; with one, only those known
+   // to have finished before it.
+   let (done, kept): (Vec<ListItem>, Vec<ListItem>) =
+      timeline.items.drain(..).partition(|item| {
+         item.is_done() == Some(true)
+            && before.is_none_or(|cutoff| item.finished_at().is_some_and(|ts| ts < cutoff))
+      });
+   timeline.items = kept;
+   if done.is_empty() {
+      info!("nothing to archive in {}", name);
+      return Ok(());
+   }
+   info!("archived {} item(s) to {}", done.len(), archive_name);
+   archive.items.extend(done);
+   archive.save()?;
+   timeline.save()
+}
+
+/// Appends to the timeline, creating it when it does not exist yet.
+/// The items go in the order their flags were given.
+fn run_add(matches: &ArgMatches, config: &config::Config) -> Result<(), INNError> {
+   let name = matches.value_of("NAME").unwrap();
+   check_name(name)?;
+   let mut timeline = match ListV1::load(name) {
+      Err(INNError::NotFound(_)) => ListV1::new(name),
+      loaded => loaded?,
+   };
+   let budget = match matches.value_of("budget") {
+      Some(budget) => Timespan::from_human(budget)?,
+      None => config.default_budget(),
+   };
+   let mut added: Vec<(usize, ListItem)> = vec![];
+   for flag in ["goal", "note", "heading", "entry", "timebox"].iter() {
+      if let (Some(indices), Some(values)) = (matches.indices_of(flag), matches.values_of(flag)) {
+         for (idx, text) in indices.zip(values) {
+            let text = text.to_string();
+            let item = match *flag {
+               "goal" => ListItem::Goal(Goal::new(text, false)),
+               "note" => ListItem::Note(text),
+               "heading" => ListItem::Heading(text),
+               "entry" => ListItem::Entry(text),
+               _ => {
+                  let mut tb = CheckTimebox::new(text, None);
+                  tb.budget = budget.clone();
+                  ListItem::Timebox(tb)
+               }
+            };
+            added.push((idx, item));
+         }
+      }
+   }
+   added.sort_by_key(|(idx, _)| *idx);
+   timeline
+      .items
+      .extend(added.into_iter().map(|(_, item)| item));
+   // Subtasks go last so they can name a parent added alongside them.
+   for subtask in matches.values_of("subtask").into_iter().flatten() {
+      let (parent, text) = match subtask.find(':') {
+         Some(colon) => (&subtask[..colon], &subtask[colon + 1..]),
+         None => return Err(INNError::NoSuchItem(subtask.to_string())),
+      };
+      let idx = select_item(&timeline, parent)?;
+      match &mut timeline.items[idx] {
+         ListItem::Goal(goal) => goal.subtasks.push(Goal::new(text.to_string(), false)),
+         item => return Err(INNError::NotAGoal(item.label().to_string())),
+      }
+   }
+   timeline.save()
+}
+
+/// The names of all the timelines in the store directory.
+fn context_names() -> std::io::Result<Vec<String>> {
+   let mut names = vec![];
+   let dir = match store_dir() {
+      dir if dir.as_os_str().is_empty() => Path::new("."),
+      dir => dir,
+   };
+   for entry in std::fs::read_dir(dir)? {
+      let filename = entry?.file_name().to_string_lossy().to_string();
+      match filename.strip_suffix(IFNOTNOW_EXTENSION) {
+         Some(name) if RESERVED_NAMES.contains(&name) => {}
+         None => {}
+         Some(name) => names.push(name.to_string()),
+      }
+   }
+   names.sort();
+   Ok(names)
+}
+
+/// What `inn list` shows about a timeline.
+#[derive(Debug, Serialize)]
+struct ContextSummary {
+   name: String,
+   items: usize,
+   created: DTUtc,
+   modified: DTUtc,
+}
+
+fn run_list(matches: &ArgMatches) -> Result<(), INNError> {
+   let mut summaries = vec![];
+   for name in context_names()?.iter() {
+      let timeline = match ListV1::load(name) {
+         Ok(timeline) => timeline,
+         Err(e) => {
+            warn!("skipping {}: {}", ListV1::filename(name), e);
+            continue;
+         }
+      };
+      summaries.push(ContextSummary {
+         name: name.to_string(),
+         items: timeline.items.len(),
+         created: timeline.created_ts,
+         modified: timeline.modified_ts,
+      });
+   }
+   if matches.value_of("sort") == Some("recent") {
+      summaries.sort_by_key(|summary| std::cmp::Reverse(summary.modified));
+   }
+   let (summaries, footer) = page(summaries, matches)?;
+   if matches.is_present("json") {
+      println!("{}", serde_json::to_string(&summaries)?);
+   } else {
+      for summary in summaries.iter() {
+         println!(
+            "{}\t{} items\t{}",
+            summary.name,
+            summary.items,
+            summary.modified.format("%Y-%m-%d %H:%M")
+         );
+      }
+      if let Some(footer) = footer {
+         println!("{}", footer);
+      }
+   }
+   Ok(())
+}
+
+/// What `inn stats` shows about a timeline. Items in sublists are
+/// counted, the sublists themselves are not.
+#[derive(Debug, Default, Serialize)]
+struct ContextStats {
+   items: usize,
+   goals_done: usize,
+   goals_open: usize,
+   timeboxes: usize,
+   accrued: Timespan,
+   budget: Timespan,
+   waiting: usize,
+   over_budget: usize,
+   tags: BTreeMap<String, usize>,
+}
+impl ContextStats {
+   fn of(list: &ListV1) -> ContextStats {
+      fn count(list: &ListV1, stats: &mut ContextStats) {
+         for item in list.items.iter() {
+            match item {
+               ListItem::Sublist(sub) => {
+                  count(sub, stats);
+                  continue;
+               }
+               ListItem::Goal(goal) if goal.is_done() => stats.goals_done += 1,
+               ListItem::Goal(_) => stats.goals_open += 1,
+               ListItem::Timebox(tb) => {
+                  stats.timeboxes += 1;
+                  stats.accrued += tb.recompute_accrued();
+                  stats.budget += tb.budget.clone();
+                  if tb.waiting_for().is_some() {
+                     stats.waiting += 1;
+                  }
+                  if tb.overage().is_some() {
+                     stats.over_budget += 1;
+                  }
+               }
+               _ => {}
+            }
+            stats.items += 1;
+         }
+      }
+      let mut stats = ContextStats::default();
+      count(list, &mut stats);
+      for (tag, indices) in list.tags().iter() {
+         stats.tags.insert(tag.to_string(), indices.len());
+      }
+      stats
+   }
+}
+
+fn run_stats(matches: &ArgMatches) -> Result<(), INNError> {
+   let stats = ContextStats::of(&ListV1::load(matches.value_of("NAME").unwrap())?);
+   if matches.is_present("json") {
+      println!("{}", serde_json::to_string(&stats)?);
+      return Ok(());
+   }
+   println!("items: {}", stats.items);
+   println!(
+      "goals: {} done, {} open",
+      stats.goals_done, stats.goals_open
+   );
+   println!(
+      "timeboxes: {}, {} accrued of {} budgeted",
+      stats.timeboxes, stats.accrued, stats.budget
+   );
+   println!("waiting: {}", stats.waiting);
+   println!("over budget: {}", stats.over_budget);
+   for (tag, n) in stats.tags.iter() {
+      println!("#{}: {}", tag, n);
+   }
+   Ok(())
+}
+
+/// Timelines that do not load are left out with a warning, as in
+/// `search --all`.
+fn run_report(matches: &ArgMatches) -> Result<(), INNError> {
+   let period = if matches.is_present("day") {
+      report::Period::Day
+   } else if matches.is_present("month") {
+      report::Period::Month
+   } else {
+      report::Period::Week
+   };
+   let now = Utc::now();
+   let mut report = report::TimeReport::new(period.start(now), now);
+   for name in context_names()?.iter() {
+      match ListV1::load(name) {
+         Ok(list) => report.add(&list),
+         Err(e) => warn!("skipping {}: {}", ListV1::filename(name), e),
+      }
+   }
+   if matches.is_present("json") {
+      println!("{}", serde_json::to_string(&report)?);
+      return Ok(());
+   }
+   println!(
+      "{} to {}",
+      report.from.format("%Y-%m-%d %H:%M"),
+      report.until.format("%Y-%m-%d %H:%M")
+   );
+   for (name, accrued) in report.timelines.iter() {
+      println!("{}: {}", name, accrued);
+   }
+   for (tag, accrued) in report.tags.iter() {
+      println!("#{}: {}", tag, accrued);
+   }
+   println!("total: {}", report.total);
+   println!("goals finished: {}", report.goals_finished);
+   Ok(())
+}
+
+/// Runs the query against each named timeline, returning the timelines
+/// with at least one hit, most hits first.
+fn search_contexts(names: &[String], query: &Query) -> Result<Vec<(String, usize)>, INNError> {
+   let mut hits = vec![];
+   for name in names.iter() {
+      let count = match query {
+         Query::ContextNames(pattern) => pattern.count_in(name),
+         Query::ContextItems(pattern) => ListV1::load(name)?.matches(pattern.clone()),
+         Query::ContextItemsDone(pattern, done) => ListV1::load(name)?
+            .retain_items(&|item| item.is_done() == Some(*done))
+            .matches(pattern.clone()),
+      };
+      match count {
+         Ok(0) => {}
+         Ok(n) => hits.push((name.to_string(), n)),
+         Err(e) => return Err(INNError::Pattern(e)),
+      }
+   }
+   hits.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+   Ok(hits)
+}
+
+/// Prints the rendered lines of the matching items grep-style, as
+/// `name.inn.yaml:INDEX:line`. Items in sublists are indexed as
+/// `SUBLIST/INDEX`. On a terminal the hits are highlighted. With `done`
+/// only the goals and timeboxes in that state are looked at.
+fn show_hits(
+   name: &str,
+   list: &ListV1,
+   pattern: &Pattern,
+   done: Option<bool>,
+   prefix: &str,
+   links: &LinkTargets,
+) -> Result<(), INNError> {
+   for (n, item) in list.items.iter().enumerate() {
+      let at = format!("{}{}", prefix, n + 1);
+      if let ListItem::Sublist(sub) = item {
+         show_hits(name, sub, pattern, done, &format!("{}/", at), links)?;
+         continue;
+      }
+      if done.is_some() && item.is_done() != done {
+         continue;
+      }
+      if item.matches(pattern.clone()).map_err(INNError::Pattern)? == 0 {
+         continue;
+      }
+      let rendered = render_item(item, "", Utc::now(), links);
+      let rendered: Vec<&str> = rendered.lines().collect();
+      let mut lines: Vec<String> = vec![];
+      // The texts come in the order they are rendered, so each is looked
+      // for from the line the last one was found on.
+      let mut from = 0;
+      for (text, spans) in hit_texts(item, pattern).map_err(INNError::Pattern)? {
+         if spans.is_empty() {
+            continue;
+         }
+         let found = rendered[from..]
+            .iter()
+            .enumerate()
+            .find_map(|(i, line)| line.find(text.as_str()).map(|at| (from + i, at)));
+         match found {
+            Some((i, at)) => {
+               let shifted: Vec<_> = spans.iter().map(|(s, e)| (s + at, e + at)).collect();
+               lines.push(highlight(rendered[i], &shifted));
+               from = i;
+            }
+            // Wrapped, or not shown at all like an earlier reason for
+            // waiting.
+            None => lines.push(highlight(&text, &spans)),
+         }
+      }
+      for line in lines.iter() {
+         println!("{}{}:{}:{}", name, IFNOTNOW_EXTENSION, at, line);
+      }
+   }
+   Ok(())
+}
+
+/// A text an item is searched in, with the spans of the hits in it.
+type HitText = (String, Vec<(usize, usize)>);
+
+/// Each text an item is searched in: a goal's label and those of its
+/// subtasks, a timebox's label and reasons.
+fn hit_texts(item: &ListItem, pattern: &Pattern) -> Result<Vec<HitText>, PatternErr> {
+   fn goal_texts(
+      goal: &Goal,
+      pattern: &Pattern,
+      texts: &mut Vec<HitText>,
+   ) -> Result<(), PatternErr> {
+      texts.push((goal.label.clone(), goal.match_spans(pattern)?));
+      for sub in goal.subtasks.iter() {
+         goal_texts(sub, pattern, texts)?;
+      }
+      Ok(())
+   }
+   let mut texts = vec![];
+   match item {
+      ListItem::Goal(goal) => goal_texts(goal, pattern, &mut texts)?,
+      ListItem::Timebox(tb) => {
+         let spans = tb.match_spans(pattern)?;
+         let mut start = 0;
+         for part in tb.searched_text().split('\n') {
+            let end = start + part.len();
+            let inside = spans
+               .iter()
+               .filter(|(s, e)| *s >= start && *e <= end)
+               .map(|(s, e)| (s - start, e - start))
+               .collect();
+            texts.push((part.to_string(), inside));
+            start = end + 1;
+         }
+      }
+      _ => texts.push((item.label().to_string(), item.match_spans(pattern)?)),
+   }
+   Ok(texts)
+}
+
+/// Marks the spans in red when in color.
+fn highlight(line: &str, spans: &[(usize, usize)]) -> String {
+   if !color() {
+      return line.to_string();
+   }
+   let mut out = String::from("");
+   let mut last = 0;
+   for (start, end) in spans.iter() {
+      out.push_str(&line[last..*start]);
+      out.push_str(&format!("\x1b[1;31m{}\x1b[0m", &line[*start..*end]));
+      last = *end;
+   }
+   out.push_str(&line[last..]);
+   out
+}
+
+fn run_search(matches: &ArgMatches) -> Result<(), INNError> {
+   // With --all the only positional given is the pattern, and files
+   // that do not load are skipped rather than ending the search.
+   let (names, text) = if matches.is_present("all") {
+      let text = matches
+         .value_of("PATTERN")
+         .or_else(|| matches.value_of("NAME"));
+      let mut names = context_names()?;
+      names.retain(|name| match ListV1::load(name) {
+         Ok(_) => true,
+         Err(e) => {
+            warn!("skipping {}: {}", ListV1::filename(name), e);
+            false
+         }
+      });
+      (names, text.unwrap_or_default())
+   } else {
+      let name = matches.value_of("NAME").unwrap();
+      (vec![name.to_string()], matches.value_of("PATTERN").unwrap())
+   };
+   let pattern = if matches.is_present("regex") {
+      Pattern::Regex(text.to_string())
+   } else {
+      Pattern::Keyword(text.to_string())
+   };
+   // Fail before loading anything when the regex does not parse.
+   if let Some(e) = pattern.check_errors() {
+      return Err(INNError::Pattern(e));
+   }
+   debug!("searching {} timeline(s) for {:?}", names.len(), text);
+   let done = match (matches.is_present("open"), matches.is_present("done")) {
+      (true, _) => Some(false),
+      (_, true) => Some(true),
+      _ => None,
+   };
+   if matches.is_present("show") {
+      for name in names.iter() {
+         let timeline = ListV1::load(name)?;
+         show_hits(name, &timeline, &pattern, done, "", &LinkTargets::resolve(&timeline))?;
+      }
+      return Ok(());
+   }
+   let query = match done {
+      _ if matches.is_present("names") => Query::ContextNames(pattern),
+      Some(done) => Query::ContextItemsDone(pattern, done),
+      None => Query::ContextItems(pattern),
+   };
+   for (name, count) in search_contexts(&names, &query)?.iter() {
+      println!("{}: {}", name, count);
+   }
+   Ok(())
+}
+
+/// The time accrued against the budget, and by how much it is over.
+fn render_budget(tb: &CheckTimebox) -> String {
+   let accrued = tb.recompute_accrued();
+   match tb.overage() {
+      Some(over) => format!("(..{} <={} OVER by {})", accrued, tb.budget, over),
+      None => format!("(..{} <={})", accrued, tb.budget),
+   }
+}
+
+/// A running timebox with the time it has accrued by `now`.
+fn render_running(tb: &CheckTimebox, indent: &str, now: DTUtc) -> String {
+   let accrued = tb.accrued_at(now);
+   let over = if accrued > tb.budget {
+      format!(" OVER by {}", accrued.saturating_sub(&tb.budget))
+   } else {
+      String::from("")
+   };
+   format!(
+      "{} - [>] {} ({} running <={}{}){}\n",
+      indent,
+      tb.label,
+      accrued,
+      tb.budget,
+      over,
+      render_schedule(&tb.scheduled)
+   )
+}
+
+/// Shows when a scheduled item begins and ends, or nothing.
+fn render_schedule(scheduled: &Option<Event>) -> String {
+   let fmt = |ts: Option<DTUtc>| ts.map(|ts| ts.format("%Y-%m-%d %H:%M").to_string());
+   match scheduled {
+      Some(event) => format!(
+         " (scheduled {}..{})",
+         fmt(event.begins).unwrap_or_default(),
+         fmt(event.ends).unwrap_or_default()
+      ),
+      None => String::new(),
+   }
+}
+
+fn render_goal(goal: &Goal, indent: &str) -> String {
+   let done = goal.is_done();
+   let mut out = format!(
+      "{} - [{}] {}{}\n",
+      indent,
+      if done { "x" } else { " " },
+      if done {
+         format!("~~{}~~", &goal.label)
+      } else {
+         String::from(&goal.label)
+      },
+      render_schedule(&goal.scheduled)
+   );
+   for sub in goal.subtasks.iter() {
+      out.push_str(&render_goal(sub, &format!("{}   ", indent)));
+   }
+   out
+}
+
+/// The target of a link, with `#N` for the item it points at.
+fn link_href(link: &Link) -> String {
+   match link.item {
+      Some(item) => format!("{}#{}", link.target, item),
+      None => link.target.to_string(),
+   }
+}
+
+/// One item as shown in a timeline, running timeboxes accrued up to `now`.
+/// Links are checked against `links`.
+fn render_item(item: &ListItem, indent: &str, now: DTUtc, links: &LinkTargets) -> String {
+   match item {
+      ListItem::Heading(txt) => format!("{}## {}\n", indent, txt),
+      ListItem::Note(txt) => wrap(&format!("{}> ", indent), txt, width()),
+      ListItem::Goal(cb) => render_goal(cb, indent),
+      ListItem::Timebox(tb) => match tb.done {
+         Some(done) => format!(
+            "{} - [x] ~~{}~~ {} done {}{}\n",
+            indent,
+            tb.label,
+            render_budget(tb),
+            done.format("%Y-%m-%d %H:%M"),
+            render_schedule(&tb.scheduled)
+         ),
+         None => match tb.waiting_for() {
+            Some(reason) => format!(
+               "{} - [~] {} (waiting: {}) {}{}\n",
+               indent,
+               tb.label,
+               reason,
+               render_budget(tb),
+               render_schedule(&tb.scheduled)
+            ),
+            None if tb.is_running() => render_running(tb, indent, now),
+            None => format!(
+               "{} - [{}] {} {}{}\n",
+               indent,
+               if tb.overage().is_some() { "!" } else { " " },
+               tb.label,
+               render_budget(tb),
+               render_schedule(&tb.scheduled)
+            ),
+         },
+      },
+      ListItem::Entry(ent) => wrap(&format!("{} - ", indent), ent, width()),
+      ListItem::Sublist(sub) => render_list(sub, &format!("{}   ", indent), 0, None, now, links),
+      ListItem::Place(p) => format!("{} - @ {} ({:.4}, {:.4})\n", indent, p.label, p.lat, p.lon),
+      ListItem::Link(link) => format!(
+         "{} -> [{}]({}){}\n",
+         indent,
+         link.label,
+         link_href(link),
+         if link.is_broken(links) { " (broken)" } else { "" }
+      ),
+   }
+}
+
+/// The text after `prefix`, broken between words to fit in `width`
+/// columns. Lines after the first are indented to line up under the
+/// text. A word too long for a line gets one to itself.
+fn wrap(prefix: &str, text: &str, width: Option<usize>) -> String {
+   let width = match width {
+      Some(width) => width,
+      None => return format!("{}{}\n", prefix, text),
+   };
+   let hang = " ".repeat(prefix.chars().count());
+   let mut out = String::from("");
+   let mut line = prefix.to_string();
+   let mut empty = true;
+   for word in text.split_whitespace() {
+      if !empty && line.chars().count() + 1 + word.chars().count() > width {
+         out.push_str(&line);
+         out.push('\n');
+         line = hang.to_string();
+         empty = true;
+      }
+      if !empty {
+         line.push(' ');
+      }
+      line.push_str(word);
+      empty = false;
+   }
+   out.push_str(&line);
+   out.push('\n');
+   out
+}
+
+fn render_json(list: &ListV1) -> Result<String, INNError> {
+   Ok(serde_json::to_string_pretty(list)?)
+}
+
+/// Renders the items of a list nested `depth` sublists deep. Sublists
+/// deeper than `max_depth` are collapsed into a placeholder line.
+fn render_list(
+   list: &ListV1,
+   indent: &str,
+   depth: usize,
+   max_depth: Option<usize>,
+   now: DTUtc,
+   links: &LinkTargets,
+) -> String {
+   let mut out = String::from("");
+   for x in list.items.iter() {
+      match x {
+         ListItem::Sublist(sub) if max_depth.is_some_and(|max| depth >= max) => {
+            out.push_str(&render_collapsed(sub, indent))
+         }
+         ListItem::Sublist(sub) => out.push_str(&render_list(
+            sub,
+            &format!("{}   ", indent),
+            depth + 1,
+            max_depth,
+            now,
+            links,
+         )),
+         _ => out.push_str(&render_item(x, indent, now, links)),
+      }
+   }
+   out
+}
+
+fn render_collapsed(sub: &ListV1, indent: &str) -> String {
+   format!("{} - [+] {} ({} items hidden)\n", indent, sub.name, sub.items.len())
+}
+
+/// The items as a tree under the timeline's name, drawn with box
+/// connectors. Subtasks hang under their goals and sublists are
+/// branches.
+fn render_tree(list: &ListV1) -> String {
+   enum Node<'a> {
+      Item(&'a ListItem),
+      Subtask(&'a Goal),
+   }
+   // The first line an item renders as, without the list marker.
+   fn line(node: &Node, links: &LinkTargets) -> String {
+      let rendered = match node {
+         Node::Item(ListItem::Sublist(sub)) => return sub.name.to_string(),
+         Node::Item(item) => render_item(item, "", Utc::now(), links),
+         Node::Subtask(goal) => render_goal(goal, ""),
+      };
+      let first = rendered.lines().next().unwrap_or_default().trim_start();
+      first.strip_prefix("- ").unwrap_or(first).to_string()
+   }
+   fn children<'a>(node: &Node<'a>) -> Vec<Node<'a>> {
+      match *node {
+         Node::Item(ListItem::Sublist(sub)) => sub.items.iter().map(Node::Item).collect(),
+         Node::Item(ListItem::Goal(goal)) | Node::Subtask(goal) => {
+            goal.subtasks.iter().map(Node::Subtask).collect()
+         }
+         Node::Item(_) => vec![],
+      }
+   }
+   fn draw(nodes: &[Node], prefix: &str, links: &LinkTargets, out: &mut String) {
+      for (i, node) in nodes.iter().enumerate() {
+         let last = i + 1 == nodes.len();
+         let (branch, rest) = if last {
+            ("\u{2514}\u{2500}\u{2500} ", "    ")
+         } else {
+            ("\u{251c}\u{2500}\u{2500} ", "\u{2502}   ")
+         };
+         out.push_str(&format!("{}{}{}\n", prefix, branch, line(node, links)));
+         draw(&children(node), &format!("{}{}", prefix, rest), links, out);
+      }
+   }
+   let mut out = format!("{}\n", list.name);
+   let nodes: Vec<Node> = list.items.iter().map(Node::Item).collect();
+   draw(&nodes, "", &LinkTargets::resolve(list), &mut out);
+   out
+}
+
+/// Wraps each line of the text in an ANSI style.
+fn paint(style: &str, text: &str) -> String {
+   let mut out = String::from("");
+   for line in text.lines() {
+      out.push_str(&format!("\x1b[{}m{}\x1b[0m\n", style, line));
+   }
+   out
+}
+
+/// Colors YAML line by line: comments dim, keys cyan, strings green and
+/// other scalars yellow. Good enough for what serde_yaml writes, not
+/// for YAML in general.
+fn highlight_yaml(text: &str) -> String {
+   fn scalar(value: &str) -> String {
+      let plain = matches!(value, "~" | "null" | "true" | "false") || value.parse::<f64>().is_ok();
+      match value {
+         "" | "---" | "|" | ">" | "|-" | ">-" | "[]" | "{}" => value.to_string(),
+         _ if plain => format!("\x1b[33m{}\x1b[0m", value),
+         _ => format!("\x1b[32m{}\x1b[0m", value),
+      }
+   }
+   let mut out = String::from("");
+   for line in text.lines() {
+      // Indentation and sequence dashes are left as they are.
+      let mut body = line.trim_start();
+      while let Some(rest) = body.strip_prefix("- ") {
+         body = rest.trim_start();
+      }
+      let lead = &line[..line.len() - body.len()];
+      let key_end = if body.starts_with(['"', '\'']) {
+         None
+      } else if let Some(key) = body.strip_suffix(':') {
+         Some(key.len())
+      } else {
+         body.find(": ")
+      };
+      if body.starts_with('#') {
+         out.push_str(&format!("{}\x1b[2m{}\x1b[0m\n", lead, body));
+      } else if let Some(end) = key_end {
+         out.push_str(&format!(
+            "{}\x1b[36m{}\x1b[0m:{}\n",
+            lead,
+            &body[..end],
+            match body[end + 1..].strip_prefix(' ') {
+               Some(value) => format!(" {}", scalar(value)),
+               None => String::from(""),
+            }
+         ));
+      } else {
+         out.push_str(&format!("{}{}\n", lead, scalar(body)));
+      }
+   }
+   out
+}
+
+fn render_goal_colored(goal: &Goal, indent: &str) -> String {
+   let line = render_goal(goal, indent);
+   let line = line.lines().next().unwrap_or_default();
+   // Dim and struck through when done.
+   let mut out = if goal.is_done() {
+      paint("2;9", line)
+   } else {
+      format!("{}\n", line)
+   };
+   for sub in goal.subtasks.iter() {
+      out.push_str(&render_goal_colored(sub, &format!("{}   ", indent)));
+   }
+   out
+}
+
+fn render_item_colored(item: &ListItem, indent: &str, now: DTUtc, links: &LinkTargets) -> String {
+   let plain = render_item(item, indent, now, links);
+   match item {
+      ListItem::Heading(_) => paint("1", &plain),
+      ListItem::Note(_) => paint("3;90", &plain),
+      ListItem::Goal(goal) => render_goal_colored(goal, indent),
+      ListItem::Timebox(tb) if tb.done.is_some() => paint("2", &plain),
+      ListItem::Timebox(tb) if tb.is_running() => paint("1;32", &plain),
+      ListItem::Sublist(sub) => {
+         render_list_colored(sub, &format!("{}   ", indent), 0, None, now, links)
+      }
+      _ => plain,
+   }
+}
+
+/// `render_list` with headings bold, notes gray, done goals dim and
+/// struck through and running timeboxes highlighted.
+fn render_list_colored(
+   list: &ListV1,
+   indent: &str,
+   depth: usize,
+   max_depth: Option<usize>,
+   now: DTUtc,
+   links: &LinkTargets,
+) -> String {
+   let mut out = String::from("");
+   for x in list.items.iter() {
+      match x {
+         ListItem::Sublist(sub) if max_depth.is_some_and(|max| depth >= max) => {
+            out.push_str(&paint("2", &render_collapsed(sub, indent)))
+         }
+         ListItem::Sublist(sub) => out.push_str(&render_list_colored(
+            sub,
+            &format!("{}   ", indent),
+            depth + 1,
+            max_depth,
+            now,
+            links,
+         )),
+         _ => out.push_str(&render_item_colored(x, indent, now, links)),
       }
    }
+   out
 }
 
-fn main() -> std::io::Result<()> {
-   let matches = App::new("ifnotnow")
-      .version("1.0")
-      .author("Simon Janes <spjanes@protonmail.com>")
-      .subcommand(
-         SubCommand::with_name("init").arg(
-            Arg::with_name("NAME")
-               .help("Sets the name of the new timeline")
-               .required(true)
-               .index(1),
-         ),
-      )
-      .subcommand(
-         SubCommand::with_name("add")
-            .arg(
-               Arg::with_name("NAME")
-                  .help("Sets the name of the timeline to modify")
-                  .required(true)
-                  .index(1),
-            )
-            .arg(
-               Arg::with_name("goal")
-                  .short("g")
-                  .long("goal")
-                  .help("Short description of the goal")
-                  .takes_value(true),
-            ),
-      )
-      .subcommand(
-         SubCommand::with_name("now").arg(
-            Arg::with_name("NAME")
-               .help("Sets the name of the timeline to display for now")
-               .required(true)
-               .index(1),
-         ),
-      )
-      .get_matches();
-   let cmd_queue: Vec<Cmd> = vec![];
-   match matches.subcommand() {
-      ("init", Some(args)) => {
-         let name = args.value_of("NAME").unwrap();
-         Cmd::Context(contexts::Cmd::Init(name.to_string()))
+/// The list as shown on stdout, in color if `--color` says so, with
+/// sublists below `max_depth` collapsed.
+fn render_list_shown(list: &ListV1, max_depth: Option<usize>) -> String {
+   let now = Utc::now();
+   let links = LinkTargets::resolve(list);
+   if color() {
+      render_list_colored(list, "", 0, max_depth, now, &links)
+   } else {
+      render_list(list, "", 0, max_depth, now, &links)
+   }
+}
+
+/// Groups the items under the smallest horizon around `now` that holds
+/// their timestamp. Sublists are looked into rather than shown whole,
+/// and headings are dropped since the view has its own.
+fn render_horizon_view(list: &ListV1, now: DTUtc) -> String {
+   fn collect<'a>(
+      list: &'a ListV1,
+      now: DTUtc,
+      buckets: &mut BTreeMap<Option<Horizon>, Vec<&'a ListItem>>,
+   ) {
+      for item in list.items.iter() {
+         match item {
+            ListItem::Sublist(sub) => collect(sub, now, buckets),
+            ListItem::Heading(_) => {}
+            _ => {
+               let horizon = item.timestamp().map(|ts| {
+                  *HORIZONS
+                     .iter()
+                     .find(|h| h.bucket(ts, now))
+                     .unwrap_or(&Horizon::Lifetime)
+               });
+               buckets.entry(horizon).or_default().push(item);
+            }
+         }
       }
-      ("add", Some(args)) => {
-         let name = args.value_of("NAME").unwrap();
-         let event = Event::new(ListV1::new(&name), Timespan::new(3600));
-         Cmd::Context(contexts::Cmd::Mark(name.to_string(), event))
+   }
+   let mut buckets = BTreeMap::new();
+   collect(list, now, &mut buckets);
+   let links = LinkTargets::resolve(list);
+   let mut out = String::from("");
+   // None sorts first but unscheduled items belong at the end.
+   let unscheduled = buckets.remove(&None);
+   for (horizon, items) in buckets.iter() {
+      if let Some(horizon) = horizon {
+         out.push_str(&format!("## {:?}\n", horizon));
       }
-      ("help", Some(args)) => Cmd::Help,
-      ("now", Some(args)) => Cmd::Noop,
-      _ => Cmd::Noop,
-   };
-   for cmd in cmd_queue.iter() {
-      match cmd {
-         Cmd::Context(contexts::Cmd::Init(name)) => {
-            init_timeline(name)?;
-         }
-         Cmd::Context(cxc) => {
-            // TODO realize feature behavior fragment and then send msg
+      for item in items.iter() {
+         out.push_str(&render_item(item, "", now, &links));
+      }
+   }
+   if let Some(items) = unscheduled {
+      out.push_str("## Unscheduled\n");
+      for item in items.iter() {
+         out.push_str(&render_item(item, "", now, &links));
+      }
+   }
+   out
+}
+
+/// Every item other than a sublist, in order, each with the path of
+/// sublists it is in, like `nested list / `, after `prefix`.
+fn flatten_items<'a>(list: &'a ListV1, prefix: &str) -> Vec<(String, &'a ListItem)> {
+   let mut items = vec![];
+   for item in list.items.iter() {
+      match item {
+         ListItem::Sublist(sub) => {
+            items.extend(flatten_items(sub, &format!("{}{} / ", prefix, sub.name)))
          }
-         _ => println!("{:?} not implelmented", cmd),
+         _ => items.push((prefix.to_string(), item)),
+      }
+   }
+   items
+}
+
+/// Sorts the items by label and groups them under their first letter,
+/// with a `#` group for labels that do not start with one. Headings and
+/// sublists are listed by their label.
+fn render_alpha_view(list: &ListV1) -> String {
+   let mut items: Vec<&ListItem> = list.items.iter().collect();
+   items.sort_by_key(|item| item.label().to_lowercase());
+   let links = LinkTargets::resolve(list);
+   let mut out = String::from("");
+   let mut group = None;
+   for item in items.iter() {
+      let letter = match item.label().chars().next() {
+         Some(c) if c.is_alphabetic() => c.to_uppercase().next().unwrap(),
+         _ => '#',
+      };
+      if group != Some(letter) {
+         out.push_str(&format!("# {}\n", letter));
+         group = Some(letter);
+      }
+      match item {
+         ListItem::Heading(txt) => out.push_str(&format!(" - {}\n", txt)),
+         ListItem::Sublist(sub) => out.push_str(&format!(" - {}\n", sub.name)),
+         _ => out.push_str(&render_item(item, "", Utc::now(), &links)),
       }
    }
+   out
+}
+
+fn show_timeline(name: &str) -> Result<(), INNError> {
+   let timeline = ListV1::load(name)?;
+   println!("# {}", timeline.name);
+   println!("{}", render_list_shown(&timeline, None));
+   Ok(())
+}
+
+/// The indices of the top-level items in display order. The sorts are
+/// stable, so items that compare equal keep their order. `label` sorts
+/// timeboxes by `CheckTimebox::sort_key`, `status` puts open items
+/// before done ones, and `recent` puts the timeboxes active most
+/// recently first and items without activity last.
+fn sort_order(list: &ListV1, mode: &str) -> Vec<usize> {
+   let mut order: Vec<usize> = (0..list.items.len()).collect();
+   let items = &list.items;
+   match mode {
+      "label" => order.sort_by_key(|idx| match &items[*idx] {
+         ListItem::Timebox(tb) => tb.sort_key(),
+         item => (item.label().to_lowercase(), None),
+      }),
+      "status" => order.sort_by_key(|idx| items[*idx].is_done().unwrap_or(false)),
+      "recent" => order.sort_by_key(|idx| match &items[*idx] {
+         ListItem::Timebox(tb) => std::cmp::Reverse(tb.last_activity()),
+         _ => std::cmp::Reverse(None),
+      }),
+      _ => {}
+   }
+   order
+}
 
+/// Writes the completion script for the shell to stdout.
+fn run_completions(matches: &ArgMatches) -> Result<(), INNError> {
+   // possible_values only lets through names clap knows.
+   let shell: Shell = matches.value_of("SHELL").unwrap().parse().unwrap();
+   build_cli().gen_completions_to("ifnotnow", shell, &mut std::io::stdout());
    Ok(())
 }
 
-fn init_timeline(name: &str) -> std::io::Result<()> {
-   let timeline = match name {
-      "starter" => starter_timeline(),
-      _ => ListV1::new(&name),
+/// Moves the file in one rename, so a crash leaves either the old name
+/// or the new one, and then saves the new name into it. The model's
+/// now context and view history follow the rename.
+fn run_rename(matches: &ArgMatches) -> Result<(), INNError> {
+   let (name, new_name) = (matches.value_of("NAME").unwrap(), matches.value_of("NEWNAME").unwrap());
+   check_name(new_name)?;
+   let (filename, new_filename) = (ListV1::filename(name), ListV1::filename(new_name));
+   let mut timeline = ListV1::load(name)?;
+   // Locking the one file twice would wait for itself.
+   if filename == new_filename {
+      info!("{} is already named {}", filename, new_name);
+      return Ok(());
+   }
+   if Path::new(&new_filename).exists() && !matches.is_present("force") {
+      return Err(INNError::AlreadyExists(new_filename));
+   }
+   if dryrun::dry_run() {
+      info!("dry run: not renaming {} to {}", filename, new_filename);
+      return Ok(());
+   }
+   {
+      let _lock = lock::Lock::acquire(&filename)?;
+      let _new_lock = lock::Lock::acquire(&new_filename)?;
+      if Path::new(&new_filename).exists() {
+         undo::record(new_name)?;
+      }
+      debug!("renaming {} to {}", filename, new_filename);
+      std::fs::rename(&filename, &new_filename)?;
+   }
+   timeline.name = new_name.to_string();
+   timeline.save()?;
+   let mut model = Model::load()?;
+   let renamed = |n: &mut String| {
+      if n == name {
+         *n = new_name.to_string();
+      }
    };
-   let timeline_yaml = serde_yaml::to_string(&timeline).unwrap();
-   let filename = format!("{}{}", &name, IFNOTNOW_EXTENSION);
+   model.now_context.iter_mut().for_each(renamed);
+   model.view_history.visited.iter_mut().for_each(renamed);
+   model.save()?;
+   info!("renamed {} to {}", name, new_name);
+   Ok(())
+}
 
-   if std::path::Path::new(&filename).exists() {
-      eprintln!("ERROR: {} exists, not overwriting", filename);
+fn run_clone(matches: &ArgMatches) -> Result<(), INNError> {
+   let new_name = matches.value_of("NEWNAME").unwrap();
+   check_name(new_name)?;
+   let mut timeline = ListV1::load(matches.value_of("NAME").unwrap())?;
+   if Path::new(&ListV1::filename(new_name)).exists() {
+      return Err(INNError::AlreadyExists(ListV1::filename(new_name)));
+   }
+   timeline.name = new_name.to_string();
+   if matches.is_present("fresh") {
+      timeline.reset();
    } else {
-      let mut buf = File::create(&filename)?;
-      buf.write_all(&timeline_yaml.as_bytes())?;
+      timeline.created_ts = Utc::now();
+   }
+   timeline.save()
+}
+
+/// Shows the named timeline and remembers it as the one for now, or
+/// shows the remembered one when no name is given.
+fn run_now(matches: &ArgMatches) -> Result<(), INNError> {
+   let mut model = Model::load()?;
+   let name = match (matches.value_of("NAME"), &model.now_context) {
+      (Some(name), _) => name.to_string(),
+      (None, Some(name)) => name.to_string(),
+      (None, None) => return Err(INNError::NoNowContext),
+   };
+   if matches.is_present("watch") {
+      if matches.is_present("NAME") {
+         model.now_context = Some(name.to_string());
+         model.save()?;
+      }
+      return watch::watch(&ListV1::filename(&name), &|| show_now(matches, &name));
+   }
+   show_now(matches, &name)?;
+   if matches.is_present("NAME") {
+      model.now_context = Some(name);
+      model.save()?;
    }
    Ok(())
 }
 
-fn run_add(matches: &ArgMatches) -> std::io::Result<()> {
-   let name = matches.value_of("NAME").unwrap();
-   let timeline = ListV1::load(&name);
-   match timeline {
-      Ok(mut timeline) => {
-         if let Some(goal) = matches.value_of("goal") {
-            timeline
-               .items
-               .push(ListItem::Goal(Goal::new(goal.to_string(), false)));
-         }
-         let timeline_yaml = serde_yaml::to_string(&timeline).unwrap();
-         let mut buf = File::create(ListV1::filename(&name))?;
-         buf.write_all(&timeline_yaml.as_bytes())?;
-         Ok(())
+/// Prints the timeline the way the flags of `now` ask for.
+fn show_now(matches: &ArgMatches, name: &str) -> Result<(), INNError> {
+   let mut timeline = ListV1::load(name)?;
+   let filters = filter::from_args(matches)?;
+   if !filters.is_empty() {
+      let combine = if matches.is_present("any") {
+         filter::Combine::Or
+      } else {
+         filter::Combine::And
+      };
+      timeline = timeline.retain_items(&|item| filter::matches_item(item, &filters, combine));
+   }
+   let mut hidden = None;
+   if matches.is_present("collapse-done") {
+      let kept = timeline.retain_items(&|item| item.is_done() != Some(true));
+      let done = timeline.retain_items(&|item| item.is_done() == Some(true));
+      hidden = Some(done.count_items());
+      timeline = kept;
+   } else if matches.is_present("only-done") {
+      timeline = timeline.retain_items(&|item| item.is_done() == Some(true));
+   }
+   if matches.is_present("flat") {
+      timeline.items = flatten_items(&timeline, "")
+         .into_iter()
+         .map(|(path, item)| {
+            let mut item = item.clone();
+            item.label_mut().insert_str(0, &path);
+            item
+         })
+         .collect();
+   }
+   if let Some(mode) = matches.value_of("sort") {
+      let order = sort_order(&timeline, mode);
+      timeline.items = order
+         .iter()
+         .map(|idx| timeline.items[*idx].clone())
+         .collect();
+   }
+   let depth = count_arg(matches, "depth")?;
+   let width = match count_arg(matches, "width")? {
+      Some(width) => Some(width),
+      None if std::io::stdout().is_terminal() => {
+         crossterm::terminal::size().ok().map(|(cols, _)| cols as usize)
+      }
+      None => None,
+   };
+   WIDTH.get_or_init(|| width);
+   let (items, footer) = page(timeline.items, matches)?;
+   timeline.items = items;
+   if matches.value_of("format") == Some("json") {
+      println!("{}", render_json(&timeline)?);
+   } else {
+      println!("# {}", timeline.name);
+      match matches.value_of("by") {
+         Some("horizon") => println!("{}", render_horizon_view(&timeline, Utc::now())),
+         Some("alpha") => println!("{}", render_alpha_view(&timeline)),
+         Some("geo") => println!(
+            "{}",
+            geoview::render_geo_view(&timeline, &geoview::Equirectangular::default())
+         ),
+         _ => println!("{}", render_list_shown(&timeline, depth)),
+      }
+      if let Some(n) = hidden.filter(|n| *n > 0) {
+         println!("({} done hidden)", n);
+      }
+      if let Some(footer) = footer {
+         println!("{}", footer);
       }
-      Err(e) => panic!("{:?}", e),
    }
+   Ok(())
 }
 
-fn render_list(list: &ListV1, indent: &str) -> String {
-   let mut out = String::from("");
-   for x in list.items.iter() {
-      out = match x {
-         ListItem::Heading(txt) => format!("{}{}## {}\n", out, indent, txt),
-         ListItem::Note(txt) => format!("{}{}> {}\n", out, indent, txt),
-         ListItem::Goal(cb) => {
-            format!(
-               "{}{} - [{}] {}\n",
-               out,
-               indent,
-               if cb.done { "x" } else { " " },
-               if cb.done {
-                  format!("~~{}~~", &cb.label)
-               } else {
-                  String::from(&cb.label)
-               }
-            )
-         }
-         ListItem::Timebox(tb) => {
-            format!(
-               "{}{} - [{}] {} (..{} <={})\n",
-               out, indent, "?", tb.label, tb.accrued, tb.budget
-            )
-         }
-         ListItem::Entry(ent) => {
-            format!("{}{} - {}\n", out, indent, ent)
-         }
-         ListItem::Sublist(sub) => {
-            format!("{}{}", out, render_list(sub, &"   "))
+fn run_tree(matches: &ArgMatches) -> Result<(), INNError> {
+   let timeline = ListV1::load(matches.value_of("NAME").unwrap())?;
+   print!("{}", render_tree(&timeline));
+   Ok(())
+}
+
+fn run_cat(matches: &ArgMatches) -> Result<(), INNError> {
+   let name = matches.value_of("NAME").unwrap();
+   let text = if matches.is_present("pretty") {
+      normalize_yaml(&serde_yaml::to_string(&ListV1::load(name)?)?)
+   } else {
+      let filename = ListV1::filename(name);
+      let _lock = lock::Lock::acquire(&filename)?;
+      match std::fs::read_to_string(&filename) {
+         Ok(text) => text,
+         Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(INNError::NotFound(filename))
          }
+         Err(e) => return Err(INNError::File(e)),
       }
+   };
+   if matches.is_present("highlight") {
+      print!("{}", highlight_yaml(&text));
+   } else {
+      print!("{}", text);
    }
-   out
+   Ok(())
+}
+
+fn run_tags(matches: &ArgMatches) -> Result<(), INNError> {
+   let timeline = ListV1::load(matches.value_of("NAME").unwrap())?;
+   if matches.is_present("cloud") {
+      let tags: Vec<String> = timeline
+         .unique_tags()
+         .iter()
+         .map(|tag| format!("#{}", tag))
+         .collect();
+      println!("{}", tags.join(" "));
+      return Ok(());
+   }
+   for (tag, indices) in timeline.tags().iter() {
+      println!("#{}: {}", tag, indices.len());
+   }
+   Ok(())
+}
+
+/// Timelines that do not load are left out of the graph with a warning,
+/// as in `search --all`.
+fn run_graph(matches: &ArgMatches) -> Result<(), INNError> {
+   let mut graph = graph::Graph::default();
+   for name in context_names()?.iter() {
+      match ListV1::load(name) {
+         Ok(timeline) => graph.add(&timeline),
+         Err(e) => warn!("skipping {}: {}", ListV1::filename(name), e),
+      }
+   }
+   if matches.value_of("format") == Some("json") {
+      println!("{}", serde_json::to_string_pretty(&graph)?);
+   } else {
+      print!("{}", graph.render_dot());
+   }
+   Ok(())
 }
 
-fn run_now(matches: &ArgMatches) -> std::io::Result<()> {
+fn run_people(matches: &ArgMatches) -> Result<(), INNError> {
+   let timeline = ListV1::load(matches.value_of("NAME").unwrap())?;
+   for (person, indices) in timeline.people().iter() {
+      println!("@{}: {}", person, indices.len());
+   }
+   Ok(())
+}
+
+/// Errors from the TUI are mostly our own; anything else is reported
+/// as an IO error.
+fn run_tui(matches: &ArgMatches) -> Result<(), INNError> {
+   tui::run(matches.value_of("NAME").unwrap()).map_err(|e| match e.downcast::<INNError>() {
+      Ok(e) => e,
+      Err(e) => INNError::File(std::io::Error::other(e)),
+   })
+}
+
+fn run_undo(matches: &ArgMatches) -> Result<(), INNError> {
    let name = matches.value_of("NAME").unwrap();
-   let timeline = ListV1::load(&name);
-   match timeline {
-      Ok(timeline) => {
-         println!("# {}", timeline.name);
-         println!("{}", render_list(&timeline, ""));
-         Ok(())
+   if matches.is_present("list") {
+      let journal = undo::Journal::load()?;
+      let entries: Vec<&undo::UndoEntry> = journal.entries_for(name).collect();
+      for (n, entry) in entries.iter().rev().enumerate() {
+         println!("{}\t{}", n + 1, entry.at.format("%Y-%m-%d %H:%M:%S"));
       }
-      Err(e) => panic!("{:?}", e),
+      return Ok(());
    }
+   let entry = undo::restore(name)?;
+   info!(
+      "{} {} to before the change at {}",
+      if dryrun::dry_run() { "would restore" } else { "restored" },
+      name,
+      entry.at.format("%Y-%m-%d %H:%M:%S")
+   );
+   Ok(())
 }
 
-fn starter_timeline() -> ListV1 {
-   let mut timeline = ListV1::new(&"Your Starter Timeline");
-   timeline.items.push(ListItem::Heading(String::from(
-      "Welcome to Your Starter Timeline",
-   )));
-   timeline.items.push(ListItem::Note(String::from(
-      "This is an example timeline that shows the kinds of items you can capture in them.",
-   )));
-   timeline
-      .items
-      .push(ListItem::Goal(Goal::new("A TODO Item".to_string(), false)));
-   timeline.items.push(ListItem::Goal(Goal::new(
-      "A done TODO Item".to_string(),
-      true,
-   )));
-   timeline
-      .items
-      .push(ListItem::Goal(Goal::new("A TODO Item".to_string(), false)));
-   timeline.items.push(ListItem::Timebox(CheckTimebox::new(
-      "A Second TODO Item".to_string(),
-      Some(Utc::now()),
-   )));
-   timeline
-      .items
-      .push(ListItem::Sublist(ListV1::new("nested list")));
-   timeline
+/// The edit is journaled for `undo` like a save. A timeline that no
+/// longer loads after editing can be reopened; declining keeps the file
+/// as it was left.
+fn run_edit(matches: &ArgMatches, config: &config::Config) -> Result<(), INNError> {
+   let name = matches.value_of("NAME").unwrap();
+   ListV1::load(name)?;
+   let editor = std::env::var("EDITOR")
+      .ok()
+      .or_else(|| config.editor.clone())
+      .unwrap_or_else(|| String::from("vi"));
+   let filename = ListV1::filename(name);
+   if dryrun::dry_run() {
+      info!("dry run: not editing {}", filename);
+      return Ok(());
+   }
+   undo::record(name)?;
+   loop {
+      let mut words = editor.split_whitespace();
+      let status = std::process::Command::new(words.next().unwrap_or("vi"))
+         .args(words)
+         .arg(&filename)
+         .status()?;
+      if !status.success() {
+         return Err(INNError::EditorFailed(editor));
+      }
+      let e = match ListV1::load(name) {
+         Ok(_) => return Ok(()),
+         Err(e) => e,
+      };
+      // Nobody is there to answer when quiet.
+      if verbosity::verbosity() < verbosity::Verbosity::Normal {
+         return Err(e);
+      }
+      error!("{}", e);
+      eprint!("Reopen {} to fix it? [Y/n] ", filename);
+      let mut answer = String::new();
+      std::io::stdin().read_line(&mut answer)?;
+      if answer.trim().to_lowercase().starts_with('n') {
+         return Err(e);
+      }
+   }
+}
+
+/// A stored `accrued` that disagrees with the history of its timebox,
+/// found at `at`, the 1-based position with `/` between sublists.
+pub struct Drift {
+   pub at: String,
+   pub label: String,
+   pub stored: Timespan,
+   pub recomputed: Timespan,
+}
+
+/// Shows the timeboxes whose stored `accrued` has drifted from their
+/// history, without writing anything.
+fn run_diff(matches: &ArgMatches) -> Result<(), INNError> {
+   let timeline = ListV1::load(matches.value_of("NAME").unwrap())?;
+   let drift = timeline.drift();
+   if drift.is_empty() {
+      println!("{}: no drift", timeline.name);
+   }
+   for d in drift.iter() {
+      println!("{}: {:?} accrued {} -> {}", d.at, d.label, d.stored, d.recomputed);
+   }
+   Ok(())
+}
+
+/// Writes back the accrued time that `diff` reports.
+fn run_fix(matches: &ArgMatches) -> Result<(), INNError> {
+   let mut timeline = ListV1::load(matches.value_of("NAME").unwrap())?;
+   let n = timeline.drift().len();
+   if n > 0 {
+      timeline.fix_drift();
+      timeline.save()?;
+   }
+   info!("{}: fixed {} timebox(es)", timeline.name, n);
+   Ok(())
+}
+
+/// Reports each problem as `name: where: what`, where is the 1-based
+/// position of the item, and fails when any timeline has one.
+fn run_validate(matches: &ArgMatches) -> Result<(), INNError> {
+   let names = match matches.value_of("NAME") {
+      Some(name) if !matches.is_present("all") => vec![name.to_string()],
+      _ => context_names()?,
+   };
+   let mut invalid = 0;
+   for name in names.iter() {
+      let problems = match ListV1::load(name) {
+         Ok(timeline) => timeline.problems(),
+         Err(e) => vec![e.to_string()],
+      };
+      if problems.is_empty() {
+         println!("{}: ok", name);
+      } else {
+         invalid += 1;
+      }
+      for problem in problems.iter() {
+         println!("{}: {}", name, problem);
+      }
+   }
+   if invalid > 0 {
+      return Err(INNError::Invalid(invalid));
+   }
+   Ok(())
+}
+
+fn run_roll(matches: &ArgMatches) -> Result<(), INNError> {
+   let mut timeline = ListV1::load(matches.value_of("NAME").unwrap())?;
+   let rolled = recurrence::roll(&mut timeline, Utc::now())?;
+   for (label, due) in rolled.iter() {
+      println!("{}: due {}", label, due.format("%Y-%m-%d %H:%M"));
+   }
+   if rolled.is_empty() {
+      return Ok(());
+   }
+   timeline.save()
+}
+
+fn run_import(matches: &ArgMatches) -> Result<(), INNError> {
+   let name = matches.value_of("NAME").unwrap();
+   check_name(name)?;
+   let mut timeline = match ListV1::load(name) {
+      Err(INNError::NotFound(_)) => ListV1::new(name),
+      loaded => loaded?,
+   };
+   if matches.is_present("markdown") {
+      let filename = matches.value_of("FILE").unwrap();
+      let text = match std::fs::read_to_string(filename) {
+         Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(INNError::NotFound(filename.to_string()))
+         }
+         read => read?,
+      };
+      timeline.items.extend(markdown::parse(&text));
+      return timeline.save();
+   }
+   let ttxt = todotxt::TTxt::load(matches.value_of("FILE").unwrap().to_string())?;
+   for task in ttxt.tasks() {
+      timeline.items.push(ListItem::from_todotxt(task));
+   }
+   timeline.save()
+}
+
+fn run_export(matches: &ArgMatches) -> Result<(), INNError> {
+   fn collect(list: &ListV1, lines: &mut Vec<String>) {
+      for item in list.items.iter() {
+         match item {
+            ListItem::Sublist(sub) => collect(sub, lines),
+            _ => lines.extend(item.to_todotxt()),
+         }
+      }
+   }
+   let timeline = ListV1::load(matches.value_of("NAME").unwrap())?;
+   if dryrun::dry_run() {
+      info!("dry run: not writing {}", matches.value_of("FILE").unwrap());
+      return Ok(());
+   }
+   if matches.is_present("markdown") {
+      std::fs::write(
+         matches.value_of("FILE").unwrap(),
+         markdown::render(&timeline),
+      )?;
+      return Ok(());
+   }
+   let mut ttxt = todotxt::TTxt { items: vec![] };
+   collect(&timeline, &mut ttxt.items);
+   ttxt.save(matches.value_of("FILE").unwrap().to_string())
+}
+
+/// `view` without a subcommand. With one it is queued as the
+/// `contexts::Cmd` for it instead.
+fn run_view(matches: &ArgMatches) -> Result<(), INNError> {
+   info!("{}", matches.usage());
+   Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+
+   fn list_of(labels: &[&str]) -> ListV1 {
+      let mut list = ListV1::new("test");
+      for label in labels {
+         list.items.push(ListItem::Goal(Goal::new(label.to_string(), false)));
+      }
+      list
+   }
+
+   #[test]
+   fn find_item_by_index_label_and_substring() {
+      let list = list_of(&["write", "review", "rewrite"]);
+      assert_eq!(list.find_item("2"), Ok(1));
+      assert_eq!(list.find_item("write"), Ok(0));
+      assert_eq!(list.find_item("view"), Ok(1));
+   }
+
+   #[test]
+   fn find_item_not_found() {
+      let list = list_of(&["write", "review"]);
+      assert_eq!(list.find_item("lunch"), Err(SelectError::NotFound));
+      assert_eq!(list.find_item("3"), Err(SelectError::NotFound));
+   }
+
+   #[test]
+   fn find_item_ambiguous_substring() {
+      let list = list_of(&["write", "rewrite", "review"]);
+      assert_eq!(list.find_item("rit"), Err(SelectError::Ambiguous(vec![0, 1])));
+   }
+
+   #[test]
+   fn find_item_ambiguous_exact_label() {
+      let list = list_of(&["A TODO Item", "A done TODO Item", "A TODO Item"]);
+      assert_eq!(
+         list.find_item("A TODO Item"),
+         Err(SelectError::Ambiguous(vec![0, 2]))
+      );
+   }
+
+   fn at(ts: &str) -> DTUtc {
+      DateTime::parse_from_rfc3339(ts).unwrap().with_timezone(&Utc)
+   }
+
+   fn running_list(started: DTUtc) -> ListV1 {
+      let mut tb = CheckTimebox::new("write".to_string(), None);
+      tb.history.push(AttentionEvent::Started(started));
+      let mut list = ListV1::new("test");
+      list.items.push(ListItem::Timebox(tb));
+      list
+   }
+
+   #[test]
+   fn render_list_accrues_running_timebox_up_to_now() {
+      let started = at("2024-03-01T09:00:00Z");
+      let list = running_list(started);
+      let now = started + chrono::Duration::minutes(30);
+      let links = LinkTargets::default();
+      assert_eq!(render_list(&list, "", 0, None, now, &links), " - [>] write (30m running <=1h)\n");
+   }
+
+   #[test]
+   fn render_list_shows_running_timebox_over_budget() {
+      let started = at("2024-03-01T09:00:00Z");
+      let list = running_list(started);
+      let now = started + chrono::Duration::minutes(90);
+      assert_eq!(
+         render_list(&list, "", 0, None, now, &LinkTargets::default()),
+         " - [>] write (1h30m running <=1h OVER by 30m)\n"
+      );
+   }
+
+   /// An invocation of each subcommand, and of each `view` subcommand.
+   const INVOCATIONS: &[&[&str]] = &[
+      &["init", "t"],
+      &["add", "t"],
+      &["now", "t"],
+      &["search", "t", "x"],
+      &["start", "t", "-i", "1"],
+      &["stop", "t", "-i", "1"],
+      &["wait", "t", "-i", "1", "--for", "x"],
+      &["view"],
+      &["view", "switch", "t"],
+      &["view", "last"],
+      &["view", "next"],
+      &["view", "clear"],
+      &["import", "f", "t", "--todotxt"],
+      &["export", "t", "f", "--todotxt"],
+      &["list"],
+      &["done", "t", "-i", "1"],
+      &["undone", "t", "-i", "1"],
+      &["remove", "t", "-i", "1"],
+      &["tags", "t"],
+      &["people", "t"],
+      &["tui", "t"],
+      &["stats", "t"],
+      &["schedule", "t", "-i", "1", "--begins", "2024-03-01"],
+      &["undo", "t"],
+      &["move", "t", "-i", "1", "--to", "2"],
+      &["edit", "t"],
+      &["validate", "t"],
+      &["roll", "t"],
+      &["completions", "bash"],
+      &["diff", "t"],
+      &["fix", "t"],
+      &["up", "t", "-i", "1"],
+      &["down", "t", "-i", "1"],
+      &["archive", "t"],
+      &["graph"],
+      &["tree", "t"],
+      &["cat", "t"],
+      &["rename", "t", "u"],
+      &["clone", "t", "u"],
+      &["templates"],
+      &["report"],
+      &["budget", "t", "-i", "1", "--set", "1h"],
+   ];
+
+   #[test]
+   fn every_subcommand_maps_to_one_cmd() {
+      let mut invoked: Vec<&str> = INVOCATIONS.iter().map(|argv| argv[0]).collect();
+      invoked.dedup();
+      let cli = build_cli();
+      let names: Vec<&str> = cli.p.subcommands.iter().map(|sub| sub.get_name()).collect();
+      assert_eq!(invoked, names);
+      let mut cmds = vec![];
+      for argv in INVOCATIONS.iter() {
+         let argv = std::iter::once("inn").chain(argv.iter().copied());
+         let matches = build_cli().get_matches_from(argv);
+         let cmd = subcommand_cmd(&matches);
+         assert_ne!(cmd, Cmd::Noop);
+         assert!(!cmds.contains(&cmd), "{:?} is queued for two subcommands", cmd);
+         cmds.push(cmd);
+      }
+      assert_eq!(
+         cmds[8..12],
+         [
+            Cmd::Context(contexts::Cmd::Switch("t".to_string())),
+            Cmd::Context(contexts::Cmd::Last),
+            Cmd::Context(contexts::Cmd::Next),
+            Cmd::Context(contexts::Cmd::Clear),
+         ]
+      );
+   }
+
+   #[test]
+   fn build_cli_subcommand_names() {
+      let cli = build_cli();
+      let names: Vec<&str> = cli.p.subcommands.iter().map(|sub| sub.get_name()).collect();
+      assert_eq!(
+         names,
+         [
+            "init", "add", "now", "search", "start", "stop", "wait", "view", "import", "export",
+            "list", "done", "undone", "remove", "tags", "people", "tui", "stats", "schedule",
+            "undo", "move", "edit", "validate", "roll", "completions", "diff", "fix", "up",
+            "down", "archive", "graph", "tree", "cat", "rename", "clone", "templates", "report",
+            "budget",
+         ]
+      );
+   }
+
+   #[test]
+   fn completions_cover_the_subcommands() {
+      let mut script = vec![];
+      build_cli().gen_completions_to("ifnotnow", Shell::Bash, &mut script);
+      let script = String::from_utf8(script).unwrap();
+      for sub in build_cli().p.subcommands.iter() {
+         assert!(script.contains(sub.get_name()), "{} is not completed", sub.get_name());
+      }
+   }
+
+   const STARTER_YAML: &str = r#"version: 1
+name: starter
+created_ts: TS
+modified_ts: TS
+items:
+  - Heading: Welcome to Your Starter Timeline
+  - Note: This is an example timeline that shows the kinds of items you can capture in them.
+  - Goal:
+      label: A TODO Item
+      done: false
+  - Goal:
+      label: A done TODO Item
+      done: true
+  - Goal:
+      label: A TODO Item
+      done: false
+  - Timebox:
+      label: A Second TODO Item
+      done: TS
+      history:
+        - Created: TS
+      accrued: 0s
+      budget: 1h
+  - Sublist:
+      name: nested list
+      items: []
+"#;
+
+   #[test]
+   fn starter_timeline_yaml() {
+      let starter = template::timeline("starter", "starter").unwrap();
+      let yaml = normalize_yaml(&serde_yaml::to_string(&starter).unwrap());
+      let ts = regex::Regex::new(r#""\d{4}-\d\d-\d\dT[\d:.]+Z""#).unwrap();
+      assert_eq!(ts.replace_all(&yaml, "TS"), STARTER_YAML);
+   }
+
+   #[test]
+   fn goal_matches_through_a_subtask_only() {
+      let mut goal = Goal::new("plan the trip".to_string(), false);
+      goal.subtasks.push(Goal::new("book the train".to_string(), false));
+      let item = ListItem::Goal(goal);
+      let pattern = Pattern::Keyword("train".to_string());
+      assert_eq!(item.matches(pattern.clone()).unwrap(), 1);
+      // The hit is in the subtask's label, not the goal's.
+      assert_eq!(item.match_spans(&pattern).unwrap(), vec![]);
+      let mut list = ListV1::new("test");
+      list.items.push(item);
+      assert_eq!(list.matches(pattern).unwrap(), 1);
+   }
+
+   #[test]
+   fn goal_counts_shared_text_once_per_label() {
+      let mut goal = Goal::new("write".to_string(), false);
+      goal.subtasks.push(Goal::new("write".to_string(), false));
+      assert_eq!(goal.matches(Pattern::Keyword("write".to_string())).unwrap(), 2);
+   }
+
+   #[test]
+   fn event_round_trips_through_yaml() {
+      let mut list = ListV1::new("standup");
+      list.items.push(ListItem::Goal(Goal::new("say what I did".to_string(), false)));
+      let begins = at("2024-03-01T09:00:00Z");
+      let ends = at("2024-03-01T09:15:00Z");
+      for event in [
+         Event::scheduled(list.clone(), Some(begins), Some(ends)).unwrap(),
+         Event::scheduled(list.clone(), Some(begins), None).unwrap(),
+         Event::new(list, Timespan::new(900)),
+      ] {
+         let yaml = serde_yaml::to_string(&event).unwrap();
+         let read: Event = serde_yaml::from_str(&yaml).unwrap();
+         assert_eq!(read, event);
+      }
+   }
+
+   #[test]
+   fn render_list_indents_each_level_of_nesting() {
+      let mut three = ListV1::new("three");
+      three.items.push(ListItem::Entry("deepest".to_string()));
+      let mut two = ListV1::new("two");
+      two.items.push(ListItem::Heading("second".to_string()));
+      two.items.push(ListItem::Sublist(three));
+      let mut one = ListV1::new("one");
+      one.items.push(ListItem::Note("first".to_string()));
+      one.items.push(ListItem::Sublist(two));
+      one.items.push(ListItem::Goal(Goal::new("after".to_string(), false)));
+      let mut list = ListV1::new("test");
+      list.items.push(ListItem::Heading("top".to_string()));
+      list.items.push(ListItem::Sublist(one));
+      let now = at("2024-03-01T09:00:00Z");
+      assert_eq!(
+         render_list(&list, "", 0, None, now, &LinkTargets::default()),
+         "## top\n   > first\n      ## second\n          - deepest\n    - [ ] after\n"
+      );
+   }
+
+   #[test]
+   fn render_json_round_trips_the_starter_timeline() {
+      let starter = template::timeline("starter", "starter").unwrap();
+      let json = render_json(&starter).unwrap();
+      let read: ListV1 = serde_json::from_str(&json).unwrap();
+      assert_eq!(read, starter);
+   }
+
+   #[test]
+   fn timeboxes_sort_by_label_then_done() {
+      let done = at("2024-03-01T09:00:00Z");
+      let mut started = CheckTimebox::new("b".to_string(), None);
+      started.history.push(AttentionEvent::Started(done));
+      let mut list = ListV1::new("test");
+      for tb in [
+         CheckTimebox::new("c".to_string(), None),
+         CheckTimebox::new("B".to_string(), Some(done)),
+         started,
+         CheckTimebox::new("a".to_string(), None),
+      ] {
+         list.items.push(ListItem::Timebox(tb));
+      }
+      // The started one is open, so it goes before the done one, and
+      // working on it does not move it.
+      assert_eq!(sort_order(&list, "label"), [3, 2, 1, 0]);
+   }
+
+   #[test]
+   fn sort_order_by_label_ignores_case_and_keeps_ties() {
+      let list = list_of(&["beta", "Alpha", "alpha", "Gamma"]);
+      assert_eq!(sort_order(&list, "label"), [1, 2, 0, 3]);
+      assert_eq!(sort_order(&list, "none"), [0, 1, 2, 3]);
+   }
+
+   #[test]
+   fn repeated_tags_count_once_per_item() {
+      let item = ListItem::Entry("#home fix the #Home sink, #home #diy".to_string());
+      assert_eq!(item.tags(), ["home", "diy"]);
+      let twice: HashSet<Mention> = item.tags().into_iter().chain(item.tags()).collect();
+      assert_eq!(twice.len(), 2);
+      let mut list = list_of(&["paint #diy", "call @Sam about #home with @sam"]);
+      list.items.push(item);
+      assert_eq!(list.items[1].people(), ["sam"]);
+      let tags = list.tags();
+      assert_eq!(tags["home"], [1, 2]);
+      assert_eq!(tags["diy"], [0, 2]);
+      let unique: Vec<String> = list.unique_tags().into_iter().collect();
+      assert_eq!(unique, ["diy", "home"]);
+   }
+
+   #[test]
+   fn build_cli_parses_add() {
+      let matches = build_cli().get_matches_from(vec!["inn", "add", "proj", "--goal", "x"]);
+      let (name, args) = matches.subcommand();
+      assert_eq!(name, "add");
+      let args = args.unwrap();
+      assert_eq!(args.value_of("NAME"), Some("proj"));
+      assert_eq!(args.values_of("goal").unwrap().collect::<Vec<_>>(), ["x"]);
+      assert!(!args.is_present("note"));
+   }
+
+   #[test]
+   fn build_cli_parses_global_flags_and_repeated_values() {
+      let matches = build_cli().get_matches_from(vec![
+         "inn", "--dir", "/tmp/inn", "--dry-run", "remove", "proj", "-i", "1", "-i", "3",
+      ]);
+      assert_eq!(matches.value_of("dir"), Some("/tmp/inn"));
+      assert!(matches.is_present("dry-run"));
+      let args = matches.subcommand_matches("remove").unwrap();
+      assert_eq!(args.values_of("item").unwrap().collect::<Vec<_>>(), ["1", "3"]);
+   }
+
+   #[test]
+   fn build_cli_refuses_a_missing_name() {
+      let parsed = build_cli().get_matches_from_safe(vec!["inn", "add", "--goal", "x"]);
+      assert_eq!(parsed.unwrap_err().kind, clap::ErrorKind::MissingRequiredArgument);
+   }
+
+   #[test]
+   fn timebox_spans_cover_the_reasons_for_waiting() {
+      let mut tb = CheckTimebox::new("call the bank".to_string(), None);
+      let waiting = AttentionEvent::WaitingFor(at("2021-01-01T10:00:00Z"), "bank is closed".into());
+      tb.history.push(waiting);
+      let item = ListItem::Timebox(tb);
+      let pattern = Pattern::Keyword("bank".to_string());
+      assert_eq!(item.matches(pattern.clone()).unwrap(), 2);
+      assert_eq!(item.match_spans(&pattern).unwrap(), vec![(9, 13), (14, 18)]);
+      let texts = hit_texts(&item, &pattern).unwrap();
+      assert_eq!(
+         texts,
+         vec![
+            ("call the bank".to_string(), vec![(9, 13)]),
+            ("bank is closed".to_string(), vec![(0, 4)]),
+         ]
+      );
+   }
+
+   #[test]
+   fn todotxt_lines_round_trip() {
+      let lines = [
+         "x (A) 2021-02-01 2021-01-01 call mom +family",
+         "x 2021-02-01 water the plants",
+         "(B) 2021-01-03 pay the rent @home",
+         "2021-01-04 sweep",
+         "read a book",
+      ];
+      for line in lines.iter() {
+         let item = ListItem::from_todotxt(todotxt::Task::parse(line));
+         assert_eq!(item.to_todotxt().as_deref(), Some(*line));
+      }
+   }
+
+   #[test]
+   fn links_are_checked_against_the_resolved_targets() {
+      let targets = LinkTargets(
+         vec![("work".to_string(), Some(2)), ("gone".to_string(), None)]
+            .into_iter()
+            .collect(),
+      );
+      let link = |target: &str, item| Link {
+         target: target.to_string(),
+         item,
+         label: "see".to_string(),
+      };
+      assert!(!link("work", None).is_broken(&targets));
+      assert!(!link("work", Some(2)).is_broken(&targets));
+      assert!(link("work", Some(3)).is_broken(&targets));
+      assert!(link("work", Some(0)).is_broken(&targets));
+      assert!(link("gone", None).is_broken(&targets));
+      // Nothing was resolved for a target the list does not link to.
+      assert!(link("other", None).is_broken(&targets));
+   }
+
+   #[test]
+   fn recurrence_too_far_out_is_an_error() {
+      use recurrence::{Recurrence, Unit};
+      let from = at("2021-01-31T09:00:00Z");
+      let monthly = Recurrence::Monthly;
+      assert_eq!(monthly.after(from, 1).unwrap(), at("2021-02-28T09:00:00Z"));
+      let every = Recurrence::EveryN(u32::MAX, Unit::Day);
+      assert!(matches!(every.after(from, 2), Err(INNError::RecursTooFar(_))));
+      let every = Recurrence::EveryN(u32::MAX, Unit::Month);
+      assert!(matches!(every.after(from, 1), Err(INNError::RecursTooFar(_))));
+   }
 }
@@ -0,0 +1,193 @@
+//! GitHub-flavored markdown. Goals and timeboxes become task list
+//! items, and everything nested is indented two spaces under its parent
+//! so the nesting survives on GitHub and in editors.
+use super::*;
+
+fn checkbox(done: bool) -> &'static str {
+   if done {
+      "[x]"
+   } else {
+      "[ ]"
+   }
+}
+
+fn render_goal(goal: &Goal, indent: &str, out: &mut String) {
+   out.push_str(&format!(
+      "{}- {} {}\n",
+      indent,
+      checkbox(goal.is_done()),
+      goal.label
+   ));
+   for sub in goal.subtasks.iter() {
+      render_goal(sub, &format!("{}  ", indent), out);
+   }
+}
+
+/// Markdown headings cannot sit inside a list, so headings in
+/// sublists are written as bold list items.
+fn render_items(list: &ListV1, indent: &str, out: &mut String) {
+   for item in list.items.iter() {
+      match item {
+         ListItem::Heading(txt) if indent.is_empty() => out.push_str(&format!("## {}\n", txt)),
+         ListItem::Heading(txt) => out.push_str(&format!("{}- **{}**\n", indent, txt)),
+         ListItem::Note(txt) => out.push_str(&format!("{}> {}\n", indent, txt)),
+         ListItem::Entry(txt) => out.push_str(&format!("{}- {}\n", indent, txt)),
+         ListItem::Goal(goal) => render_goal(goal, indent, out),
+         ListItem::Timebox(tb) => out.push_str(&format!(
+            "{}- {} {} ({} of {})\n",
+            indent,
+            checkbox(tb.done.is_some()),
+            tb.label,
+            tb.recompute_accrued(),
+            tb.budget
+         )),
+         ListItem::Place(p) => out.push_str(&format!(
+            "{}- {} ({:.4}, {:.4})\n",
+            indent, p.label, p.lat, p.lon
+         )),
+         ListItem::Link(link) => out.push_str(&format!(
+            "{}- [{}]({})\n",
+            indent,
+            link.label,
+            link_href(link)
+         )),
+         ListItem::Sublist(sub) => {
+            out.push_str(&format!("{}- {}\n", indent, sub.name));
+            render_items(sub, &format!("{}  ", indent), out);
+         }
+      }
+   }
+}
+
+/// The timeline under a `#` heading of its name.
+pub fn render(list: &ListV1) -> String {
+   let mut out = format!("# {}\n\n", list.name);
+   render_items(list, "", &mut out);
+   out
+}
+
+/// A line and the lines indented under it.
+struct Node<'a> {
+   text: &'a str,
+   children: Vec<Node<'a>>,
+}
+
+/// Gathers the lines indented at least `min` columns from `pos` on. A
+/// line belongs under the nearest line above it that is indented less,
+/// so lists indented two or four spaces, or unevenly, all nest.
+fn nest<'a>(lines: &[(usize, &'a str)], pos: &mut usize, min: usize) -> Vec<Node<'a>> {
+   let mut nodes = vec![];
+   while *pos < lines.len() && lines[*pos].0 >= min {
+      let (indent, text) = lines[*pos];
+      *pos += 1;
+      let children = nest(lines, pos, indent + 1);
+      nodes.push(Node { text, children });
+   }
+   nodes
+}
+
+/// The text of a list item, without its `-`, `*`, `+` or `1.` marker.
+fn list_item(text: &str) -> Option<&str> {
+   for marker in ["- ", "* ", "+ "].iter() {
+      if let Some(rest) = text.strip_prefix(marker) {
+         return Some(rest.trim());
+      }
+   }
+   let digits = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(0);
+   match text[digits..].strip_prefix(". ") {
+      Some(rest) if digits > 0 => Some(rest.trim()),
+      _ => None,
+   }
+}
+
+/// Whether a list item is a task, and if so whether it is done.
+fn task(text: &str) -> Option<(bool, &str)> {
+   if let Some(rest) = text.strip_prefix("[ ]") {
+      return Some((false, rest.trim()));
+   }
+   match text
+      .strip_prefix("[x]")
+      .or_else(|| text.strip_prefix("[X]"))
+   {
+      Some(rest) => Some((true, rest.trim())),
+      None => None,
+   }
+}
+
+/// Tasks under a task become its subtasks. Anything else under it
+/// cannot be part of a goal, and is returned to follow the goal.
+fn to_goal(done: bool, label: &str, children: Vec<Node>) -> (Goal, Vec<ListItem>) {
+   let mut goal = Goal::new(label.to_string(), done);
+   let mut rest = vec![];
+   for child in children.into_iter() {
+      match list_item(child.text).and_then(task) {
+         Some((done, label)) => {
+            let (sub, more) = to_goal(done, label, child.children);
+            goal.subtasks.push(sub);
+            rest.extend(more);
+         }
+         None => rest.extend(items(vec![child])),
+      }
+   }
+   (goal, rest)
+}
+
+fn items(nodes: Vec<Node>) -> Vec<ListItem> {
+   let mut out = vec![];
+   for node in nodes.into_iter() {
+      let text = node.text;
+      if let Some(rest) = text.strip_prefix('>') {
+         out.push(ListItem::Note(rest.trim().to_string()));
+         out.extend(items(node.children));
+      } else if text.starts_with('#') {
+         let level = text.chars().take_while(|c| *c == '#').count();
+         // The level one heading is the title of the document.
+         if level > 1 {
+            out.push(ListItem::Heading(text[level..].trim().to_string()));
+         }
+         out.extend(items(node.children));
+      } else if let Some(item) = list_item(text) {
+         match task(item) {
+            Some((done, label)) => {
+               let (goal, rest) = to_goal(done, label, node.children);
+               out.push(ListItem::Goal(goal));
+               out.extend(rest);
+            }
+            None if !node.children.is_empty() => {
+               let mut sub = ListV1::sublist(item);
+               sub.items = items(node.children);
+               out.push(ListItem::Sublist(sub));
+            }
+            None => match item.strip_prefix("**").and_then(|t| t.strip_suffix("**")) {
+               Some(heading) => out.push(ListItem::Heading(heading.to_string())),
+               None => out.push(ListItem::Entry(item.to_string())),
+            },
+         }
+      } else {
+         out.push(ListItem::Entry(text.to_string()));
+         out.extend(items(node.children));
+      }
+   }
+   out
+}
+
+/// Reads task list items as goals, headings below the title as
+/// headings, blockquotes as notes, other list items as entries, and a
+/// list item with a list under it as a sublist. A tab indents as much
+/// as four spaces.
+pub fn parse(text: &str) -> Vec<ListItem> {
+   let lines: Vec<(usize, &str)> = text
+      .lines()
+      .filter(|line| !line.trim().is_empty())
+      .map(|line| {
+         let content = line.trim_start();
+         let indent = line[..line.len() - content.len()]
+            .chars()
+            .map(|c| if c == '\t' { 4 } else { 1 })
+            .sum();
+         (indent, content.trim_end())
+      })
+      .collect();
+   let mut pos = 0;
+   items(nest(&lines, &mut pos, 0))
+}
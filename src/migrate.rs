@@ -0,0 +1,159 @@
+//! Timelines on disk carry a schema `version`. Every load goes through
+//! `migrate` so older shapes can be upgraded as the schema evolves.
+use super::*;
+
+/// The version written by this build.
+pub const VERSION: u32 = 1;
+
+pub fn default_version() -> u32 {
+   VERSION
+}
+
+/// Timelines saved before they carried timestamps count as created and
+/// modified when they are read.
+pub fn default_ts() -> DTUtc {
+   Utc::now()
+}
+
+/// The timestamps of every sublist, which has none of its own on disk.
+pub fn sublist_ts() -> DTUtc {
+   Utc.timestamp(0, 0)
+}
+
+/// Reads a timeline of any known version from the text of `filename`.
+/// Documents written before the field existed have no `version` and
+/// are version 1. Fields the schema does not know are refused rather
+/// than dropped, since a misspelled key would otherwise lose its value
+/// on the next save.
+pub fn migrate(text: &str, filename: &str) -> Result<ListV1, INNError> {
+   let value: serde_yaml::Value = serde_yaml::from_str(text)?;
+   let version = match value.get("version") {
+      None => 1,
+      Some(v) => match v.as_u64() {
+         Some(v) => v,
+         None => return Err(INNError::UnknownVersion(format!("{:?}", v))),
+      },
+   };
+   match version {
+      // From the text, so that errors tell where they are.
+      1 => serde_yaml::from_str(text).map_err(|e| unknown_field(e, filename)),
+      v => Err(INNError::UnknownVersion(v.to_string())),
+   }
+}
+
+/// Names the field and line of an unknown field error, and passes any
+/// other error on as it is.
+fn unknown_field(e: serde_yaml::Error, filename: &str) -> INNError {
+   let re = regex::Regex::new(r"unknown field `([^`]*)`").unwrap();
+   let field = re.captures(&e.to_string()).map(|cap| cap[1].to_string());
+   match (field, e.location()) {
+      (Some(field), Some(at)) => INNError::UnknownField(filename.to_string(), field, at.line()),
+      _ => INNError::Yaml(e),
+   }
+}
+
+/// Sublists are written without a `version` or timestamps, since they
+/// are read as part of the timeline and take its version. They read
+/// back with `sublist_ts`, even when written with their own.
+pub mod sublist {
+   use super::*;
+
+   #[derive(Serialize)]
+   struct Nested<'a> {
+      name: &'a str,
+      items: &'a [ListItem],
+   }
+
+   pub fn serialize<S>(list: &ListV1, serializer: S) -> Result<S::Ok, S::Error>
+   where
+      S: serde::Serializer,
+   {
+      Nested {
+         name: &list.name,
+         items: &list.items,
+      }
+      .serialize(serializer)
+   }
+
+   pub fn deserialize<'de, D>(deserializer: D) -> Result<ListV1, D::Error>
+   where
+      D: serde::Deserializer<'de>,
+   {
+      Ok(ListV1 {
+         created_ts: sublist_ts(),
+         modified_ts: sublist_ts(),
+         ..ListV1::deserialize(deserializer)?
+      })
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+
+   #[test]
+   fn migrate_reads_a_timeline_without_a_version() {
+      let text = r#"name: old
+created_ts: "2021-03-01T09:00:00Z"
+modified_ts: "2021-03-01T09:00:00Z"
+items:
+  - Entry: kept
+"#;
+      let list = migrate(text, "old.inn.yaml").unwrap();
+      assert_eq!(list.version, VERSION);
+      assert_eq!(list.name, "old");
+      assert_eq!(list.items, vec![ListItem::Entry("kept".to_string())]);
+   }
+
+   #[test]
+   fn migrate_refuses_an_unknown_version() {
+      let text = "version: 2\nname: new\nitems: []\n";
+      let found = migrate(text, "new.inn.yaml");
+      assert!(matches!(found, Err(INNError::UnknownVersion(v)) if v == "2"));
+   }
+
+   #[test]
+   fn only_the_timeline_writes_a_version() {
+      let mut list = ListV1::new("top");
+      let mut sub = ListV1::sublist("sub");
+      sub.items.push(ListItem::Sublist(ListV1::sublist("subsub")));
+      list.items.push(ListItem::Sublist(sub));
+      let yaml = serde_yaml::to_string(&list).unwrap();
+      assert_eq!(yaml.matches("version:").count(), 1);
+      assert_eq!(yaml.matches("created_ts:").count(), 1);
+      assert_eq!(yaml.matches("modified_ts:").count(), 1);
+      let read = migrate(&yaml, "top.inn.yaml").unwrap();
+      assert_eq!(read, list);
+   }
+
+   #[test]
+   fn migrate_reads_a_sublist_with_its_own_version() {
+      let text = r#"name: top
+items:
+  - Sublist:
+      version: 1
+      name: sub
+      items: []
+"#;
+      let list = migrate(text, "top.inn.yaml").unwrap();
+      assert!(matches!(&list.items[0], ListItem::Sublist(sub) if sub.name == "sub"));
+   }
+
+   #[test]
+   fn migrate_dates_a_timeline_without_timestamps_when_read() {
+      let text = r#"version: 1
+name: old
+items:
+  - Sublist:
+      name: sub
+      created_ts: "2021-03-01T09:00:00Z"
+      modified_ts: "2021-03-01T09:00:00Z"
+      items: []
+"#;
+      let before = Utc::now();
+      let list = migrate(text, "old.inn.yaml").unwrap();
+      assert!(before <= list.created_ts && list.created_ts <= Utc::now());
+      assert!(before <= list.modified_ts && list.modified_ts <= Utc::now());
+      assert_eq!(list.items, vec![ListItem::Sublist(ListV1::sublist("sub"))]);
+   }
+}
@@ -0,0 +1,121 @@
+//! Goals and timeboxes that come around again. `inn roll` reopens the
+//! done ones and moves their schedule to the next time they are due.
+use super::*;
+use std::convert::TryFrom;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, PartialOrd, Ord, Eq)]
+pub enum Unit {
+   Day,
+   Week,
+   Month,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, PartialOrd, Ord, Eq)]
+pub enum Recurrence {
+   Daily,
+   Weekly,
+   Monthly,
+   EveryN(u32, Unit),
+}
+impl Recurrence {
+   fn every(&self) -> (u32, Unit) {
+      match self {
+         Recurrence::Daily => (1, Unit::Day),
+         Recurrence::Weekly => (1, Unit::Week),
+         Recurrence::Monthly => (1, Unit::Month),
+         Recurrence::EveryN(n, unit) => (*n.max(&1), *unit),
+      }
+   }
+   /// `periods` periods after `ts`. Months keep the day of the month, or
+   /// the last day of a shorter month. Fails past the dates chrono has.
+   pub fn after(&self, ts: DTUtc, periods: u32) -> Result<DTUtc, INNError> {
+      let (n, unit) = self.every();
+      let too_far = || INNError::RecursTooFar(*self);
+      let n = n.checked_mul(periods).ok_or_else(too_far)?;
+      let due = match unit {
+         Unit::Day => ts.checked_add_signed(chrono::Duration::days(n as i64)),
+         Unit::Week => ts.checked_add_signed(chrono::Duration::weeks(n as i64)),
+         Unit::Month => {
+            let months = ts.year() as i64 * 12 + ts.month0() as i64 + n as i64;
+            let year = i32::try_from(months.div_euclid(12)).map_err(|_| too_far())?;
+            let month = months.rem_euclid(12) as u32 + 1;
+            (1..=ts.day())
+               .rev()
+               .find_map(|day| NaiveDate::from_ymd_opt(year, month, day))
+               .map(|date| DateTime::from_utc(date.and_time(ts.time()), Utc))
+         }
+      };
+      due.ok_or_else(too_far)
+   }
+   /// The first time it is due after `now`, counting whole periods
+   /// from `from` so a missed period is skipped rather than piled up.
+   /// Each is counted from `from` itself so the 31st stays the 31st
+   /// after a shorter month.
+   pub fn next_due(&self, from: DTUtc, now: DTUtc) -> Result<DTUtc, INNError> {
+      let mut periods = 1;
+      while self.after(from, periods)? <= now {
+         periods += 1;
+      }
+      self.after(from, periods)
+   }
+}
+
+/// Moves the schedule to the next time it is due, keeping its length.
+/// An item that was never scheduled is due one period from now.
+fn reschedule(
+   label: &str,
+   recurrence: &Recurrence,
+   scheduled: &mut Option<Event>,
+   now: DTUtc,
+) -> Result<DTUtc, INNError> {
+   let from = scheduled
+      .as_ref()
+      .and_then(|event| event.at())
+      .unwrap_or(now);
+   let due = recurrence.next_due(from, now)?;
+   let ends = scheduled
+      .as_ref()
+      .and_then(|event| match (event.begins, event.ends) {
+         (Some(b), Some(e)) => Some(due + (e - b)),
+         _ => None,
+      });
+   let mut event = Event::new(ListV1::new(label), Timespan::new(0));
+   event.begins = Some(due);
+   event.ends = ends;
+   event.span = scheduled.as_ref().and_then(|event| event.span.clone());
+   *scheduled = Some(event);
+   Ok(due)
+}
+
+/// Reopens the done recurring items in the timeline and its sublists,
+/// returning each label with when it is due next. The completion stays
+/// in the item's history, and is recorded now for a goal marked done
+/// without one. An item due too far out to schedule fails the roll.
+pub fn roll(list: &mut ListV1, now: DTUtc) -> Result<Vec<(String, DTUtc)>, INNError> {
+   let mut rolled = vec![];
+   for item in list.items.iter_mut() {
+      match item {
+         ListItem::Goal(goal) if goal.done => {
+            if let Some(recurrence) = goal.recurrence {
+               if !matches!(goal.history.last(), Some(AttentionEvent::Finished(_))) {
+                  goal.history.push(AttentionEvent::Finished(now));
+               }
+               goal.done = false;
+               let due = reschedule(&goal.label, &recurrence, &mut goal.scheduled, now)?;
+               rolled.push((goal.label.to_string(), due));
+            }
+         }
+         ListItem::Timebox(tb) if tb.done.is_some() => {
+            if let Some(recurrence) = tb.recurrence {
+               // The Finished event recorded when it was done stays.
+               tb.done = None;
+               let due = reschedule(&tb.label, &recurrence, &mut tb.scheduled, now)?;
+               rolled.push((tb.label.to_string(), due));
+            }
+         }
+         ListItem::Sublist(sub) => rolled.extend(roll(sub, now)?),
+         _ => {}
+      }
+   }
+   Ok(rolled)
+}
@@ -0,0 +1,83 @@
+//! Time spent and goals finished across every timeline over a day, week
+//! or month, for `inn report`. Only the part of each span that falls in the period
+//! counts, so a timebox started yesterday and still running counts from
+//! midnight today in a daily report.
+use super::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+   Day,
+   Week,
+   Month,
+}
+impl Period {
+   /// When the period that `now` falls in began, at midnight UTC. Weeks
+   /// begin on Monday.
+   pub fn start(&self, now: DTUtc) -> DTUtc {
+      let today = now.naive_utc().date();
+      let first = match self {
+         Period::Day => today,
+         Period::Week => {
+            today - chrono::Duration::days(today.weekday().num_days_from_monday() as i64)
+         }
+         Period::Month => today.with_day(1).unwrap(),
+      };
+      Utc.from_utc_datetime(&first.and_hms_opt(0, 0, 0).unwrap())
+   }
+}
+
+/// The totals of a report. A timebox counts toward each of its tags,
+/// so the tags can add up to more than the total.
+#[derive(Debug, Serialize)]
+pub struct TimeReport {
+   pub from: DTUtc,
+   pub until: DTUtc,
+   pub timelines: BTreeMap<String, Timespan>,
+   pub tags: BTreeMap<String, Timespan>,
+   pub total: Timespan,
+   /// Goals with a `Finished` in the period, done now or not.
+   pub goals_finished: usize,
+}
+impl TimeReport {
+   pub fn new(from: DTUtc, until: DTUtc) -> TimeReport {
+      TimeReport {
+         from,
+         until,
+         timelines: BTreeMap::new(),
+         tags: BTreeMap::new(),
+         total: Timespan::new(0),
+         goals_finished: 0,
+      }
+   }
+   /// Adds the time accrued in the timeline, sublists included, within
+   /// the period. Timelines with none are left out.
+   pub fn add(&mut self, list: &ListV1) {
+      fn collect(list: &ListV1, report: &mut TimeReport, sum: &mut Timespan) {
+         for item in list.items.iter() {
+            match item {
+               ListItem::Sublist(sub) => collect(sub, report, sum),
+               ListItem::Goal(goal) if goal.finished_between(report.from, report.until) => {
+                  report.goals_finished += 1
+               }
+               ListItem::Timebox(tb) => {
+                  let accrued = tb.accrued_between(Some(report.from), report.until);
+                  if accrued == Timespan::new(0) {
+                     continue;
+                  }
+                  for tag in item.tags() {
+                     *report.tags.entry(tag.into()).or_default() += accrued.clone();
+                  }
+                  *sum += accrued;
+               }
+               _ => {}
+            }
+         }
+      }
+      let mut sum = Timespan::new(0);
+      collect(list, self, &mut sum);
+      if sum != Timespan::new(0) {
+         self.total += sum.clone();
+         self.timelines.insert(list.name.to_string(), sum);
+      }
+   }
+}
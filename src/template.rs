@@ -0,0 +1,121 @@
+//! The built-in timelines `inn init NAME --template` starts from. A
+//! template builds the items only; the new timeline takes its own name.
+use super::*;
+
+pub struct Template {
+   pub name: &'static str,
+   pub about: &'static str,
+   build: fn() -> ListV1,
+}
+
+pub const TEMPLATES: &[Template] = &[
+   Template {
+      name: "starter",
+      about: "An example of each kind of item",
+      build: starter,
+   },
+   Template {
+      name: "gtd",
+      about: "An inbox with lists for next actions, waiting, someday and projects",
+      build: gtd,
+   },
+   Template {
+      name: "daily",
+      about: "Planning, timeboxed work and a look back for one day",
+      build: daily,
+   },
+   Template {
+      name: "weekly-review",
+      about: "The steps of a weekly review",
+      build: weekly_review,
+   },
+];
+
+/// The timeline a template starts, named `name`.
+pub fn timeline(template: &str, name: &str) -> Result<ListV1, INNError> {
+   match TEMPLATES.iter().find(|t| t.name == template) {
+      Some(t) => Ok(ListV1 {
+         name: name.to_string(),
+         ..(t.build)()
+      }),
+      None => Err(INNError::NoSuchTemplate(template.to_string())),
+   }
+}
+
+fn goal(label: &str) -> ListItem {
+   ListItem::Goal(Goal::new(label.to_string(), false))
+}
+
+fn timebox(label: &str, budget_s: u64) -> ListItem {
+   let mut tb = CheckTimebox::new(label.to_string(), None);
+   tb.budget = Timespan::new(budget_s);
+   ListItem::Timebox(tb)
+}
+
+fn starter() -> ListV1 {
+   let mut timeline = ListV1::new("Your Starter Timeline");
+   timeline.items.push(ListItem::Heading(String::from(
+      "Welcome to Your Starter Timeline",
+   )));
+   timeline.items.push(ListItem::Note(String::from(
+      "This is an example timeline that shows the kinds of items you can capture in them.",
+   )));
+   timeline
+      .items
+      .push(ListItem::Goal(Goal::new("A TODO Item".to_string(), false)));
+   timeline.items.push(ListItem::Goal(Goal::new(
+      "A done TODO Item".to_string(),
+      true,
+   )));
+   timeline
+      .items
+      .push(ListItem::Goal(Goal::new("A TODO Item".to_string(), false)));
+   timeline.items.push(ListItem::Timebox(CheckTimebox::new(
+      "A Second TODO Item".to_string(),
+      Some(Utc::now()),
+   )));
+   timeline
+      .items
+      .push(ListItem::Sublist(ListV1::sublist("nested list")));
+   timeline
+}
+
+fn gtd() -> ListV1 {
+   let mut timeline = ListV1::new("gtd");
+   timeline.items.push(ListItem::Heading(String::from("Inbox")));
+   timeline.items.push(ListItem::Note(String::from(
+      "Capture everything here, then sort it into the lists below.",
+   )));
+   for name in ["next actions", "waiting for", "someday maybe", "projects"].iter() {
+      timeline.items.push(ListItem::Sublist(ListV1::sublist(name)));
+   }
+   timeline
+}
+
+fn daily() -> ListV1 {
+   let mut timeline = ListV1::new("daily");
+   timeline.items.push(ListItem::Heading(String::from("Today")));
+   timeline.items.push(goal("Plan the day"));
+   timeline.items.push(timebox("Focused work", 2 * 3600));
+   timeline.items.push(timebox("Email and messages", 1800));
+   timeline.items.push(goal("Look back at what got done"));
+   timeline
+}
+
+fn weekly_review() -> ListV1 {
+   let mut timeline = ListV1::new("weekly-review");
+   timeline.items.push(ListItem::Heading(String::from("Weekly review")));
+   timeline.items.push(timebox("Review", 3600));
+   for label in [
+      "Empty the inboxes",
+      "Go over last week's timelines",
+      "Look at the calendar for the coming weeks",
+      "Update projects and next actions",
+      "Pick the goals for next week",
+   ]
+   .iter()
+   {
+      timeline.items.push(goal(label));
+   }
+   timeline
+}
@@ -1,19 +1,128 @@
+use super::INNError;
+use chrono::NaiveDate;
+use std::io::prelude::*;
+
 // TTxt instead of TodoTxt because it would melt TODO scanners.
-struct TTxt {
-   items: Vec<String>,
+pub struct TTxt {
+   pub items: Vec<String>,
 }
 impl TTxt {
-   fn load(filename: String) -> TTxt {
+   pub fn load(filename: String) -> Result<TTxt, INNError> {
+      match std::fs::read_to_string(&filename) {
+         Ok(text) => Ok(TTxt {
+            items: text
+               .lines()
+               .filter(|line| !line.trim().is_empty())
+               .map(|line| line.to_string())
+               .collect(),
+         }),
+         Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(INNError::NotFound(filename)),
+         Err(e) => Err(INNError::File(e)),
+      }
+   }
+   pub fn save(&self, filename: String) -> Result<(), INNError> {
+      let mut buf = std::fs::File::create(filename)?;
+      for line in self.items.iter() {
+         writeln!(buf, "{}", line)?;
+      }
+      Ok(())
+   }
+   /// The lines that mention the query.
+   #[allow(dead_code)]
+   pub fn find(&self, query: String) -> TTxt {
       TTxt {
-         items: vec![
-            "(A) Hello".to_string(),
-            "World".to_string(),
-            "x done".to_string(),
-            "x 2021-07-06 2021-07-01 was done".to_string(),
-         ],
+         items: self
+            .items
+            .iter()
+            .filter(|line| line.contains(&query))
+            .cloned()
+            .collect(),
+      }
+   }
+   /// The lines that do not mention the query.
+   #[allow(dead_code)]
+   pub fn exclude(&self, query: String) -> TTxt {
+      TTxt {
+         items: self
+            .items
+            .iter()
+            .filter(|line| !line.contains(&query))
+            .cloned()
+            .collect(),
+      }
+   }
+   pub fn tasks(&self) -> Vec<Task> {
+      self.items.iter().map(|line| Task::parse(line)).collect()
+   }
+}
+
+/// One todo.txt line picked apart into its markers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Task {
+   pub done: bool,
+   pub priority: Option<char>,
+   pub completed: Option<NaiveDate>,
+   pub created: Option<NaiveDate>,
+   /// The line without the completion, priority and date prefixes.
+   pub text: String,
+   pub projects: Vec<String>,
+   pub contexts: Vec<String>,
+}
+impl Task {
+   pub fn parse(line: &str) -> Task {
+      let mut rest = line.trim();
+      let done = rest.starts_with("x ");
+      if done {
+         rest = rest[2..].trim_start();
+      }
+      let mut priority = None;
+      let bytes = rest.as_bytes();
+      if bytes.len() >= 4
+         && bytes[0] == b'('
+         && bytes[1].is_ascii_uppercase()
+         && bytes[2] == b')'
+         && bytes[3] == b' '
+      {
+         priority = Some(bytes[1] as char);
+         rest = rest[4..].trim_start();
+      }
+      // A completed task may carry a completion date before its creation
+      // date, an open one only the creation date.
+      let max_dates = if done { 2 } else { 1 };
+      let mut dates = vec![];
+      while dates.len() < max_dates {
+         match rest
+            .get(..10)
+            .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+         {
+            Some(date) if rest.len() == 10 || rest[10..].starts_with(' ') => {
+               dates.push(date);
+               rest = rest[10..].trim_start();
+            }
+            _ => break,
+         }
+      }
+      let (completed, created) = match (done, dates.as_slice()) {
+         (true, [completed, created]) => (Some(*completed), Some(*created)),
+         (true, [completed]) => (Some(*completed), None),
+         (false, [created]) => (None, Some(*created)),
+         _ => (None, None),
+      };
+      let tagged = |marker: char| -> Vec<String> {
+         rest
+            .split_whitespace()
+            .filter(|word| word.len() > 1 && word.starts_with(marker))
+            .map(|word| word[1..].to_string())
+            .collect()
+      };
+      Task {
+         done,
+         priority,
+         completed,
+         created,
+         projects: tagged('+'),
+         contexts: tagged('@'),
+         text: rest.to_string(),
       }
    }
-   fn save(filename: String) -> TTxt {}
-   fn find(&self, query: String) -> TTxt {}
-   fn exclude(&self, query: String) -> TTxt {}
 }
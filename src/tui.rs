@@ -1 +1,119 @@
-pub struct View;
+//! What the Larch applications draw. A `View` is plain lines of text
+//! so it can be printed, compared or handed to a terminal backend.
+//! `run` is that backend for crossterm.
+use super::contexts::{Cmd, ContextFlags, ContextMod, ContextModel};
+use super::larch::{self, LarchMinimal};
+use super::{ListItem, ViewCmd, ViewHistory};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::style::Print;
+use crossterm::terminal::{self, ClearType};
+use crossterm::{cursor, execute, queue};
+use std::io::{Stdout, Write};
+
+/// Rendered lines and the line the cursor is on.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct View {
+   pub lines: Vec<String>,
+   pub cursor: usize,
+}
+impl View {
+   /// The lines joined up, the cursor line marked with `>`.
+   pub fn render(&self) -> String {
+      let mut out = String::new();
+      for (i, line) in self.lines.iter().enumerate() {
+         let mark = if i == self.cursor { "> " } else { "  " };
+         out.push_str(mark);
+         out.push_str(line);
+         out.push('\n');
+      }
+      out
+   }
+}
+
+const KEYS: &str =
+   "up/down move  space toggle  s start  p pause  enter follow link  backspace back  q save and quit";
+
+/// Opens the named timeline full screen until `q`, which saves it.
+pub fn run(name: &str) -> Result<(), anyhow::Error> {
+   // Load before taking over the terminal so a missing timeline is
+   // reported like any other error.
+   let mut view_history = ViewHistory::default();
+   view_history.apply(&ViewCmd::Switch(name.to_string()));
+   let model = ContextMod::run(ContextFlags {
+      now_context: Some(name.to_string()),
+      view_history,
+   })?;
+   let mut out = std::io::stdout();
+   terminal::enable_raw_mode()?;
+   execute!(out, terminal::EnterAlternateScreen, cursor::Hide)?;
+   let result = interact(name, model, &mut out);
+   execute!(out, cursor::Show, terminal::LeaveAlternateScreen)?;
+   terminal::disable_raw_mode()?;
+   result
+}
+
+/// Following a link saves the timeline being left, and backspace goes
+/// back to it the same way.
+fn interact(name: &str, mut model: ContextModel, out: &mut Stdout) -> Result<(), anyhow::Error> {
+   loop {
+      let name = model.now_context.clone().unwrap_or_else(|| name.to_string());
+      let (view, msg) = ContextMod::view(&model);
+      model = larch::settle_with_effects::<ContextMod>(model, msg)?;
+      queue!(out, terminal::Clear(ClearType::All), cursor::MoveTo(0, 0))?;
+      // Raw mode does not return the carriage on a newline.
+      for line in view.render().lines() {
+         queue!(out, Print(line), Print("\r\n"))?;
+      }
+      queue!(out, Print("\r\n"), Print(KEYS))?;
+      out.flush()?;
+      let key = match event::read()? {
+         Event::Key(key) if key.kind == KeyEventKind::Press => key,
+         _ => continue,
+      };
+      let under_cursor = model
+         .contexts
+         .get(&name)
+         .and_then(|list| list.items.get(model.cursor));
+      let msg = match key.code {
+         KeyCode::Enter => match under_cursor {
+            Some(ListItem::Link(link)) if !link.is_broken(&model.links) => {
+               let link = link.clone();
+               model = larch::settle_with_effects::<ContextMod>(model, Some(Cmd::Save(name)))?;
+               model = larch::settle_with_effects::<ContextMod>(model, Some(Cmd::Switch(link.target)))?;
+               model.cursor = link.item.map_or(0, |item| item - 1);
+               continue;
+            }
+            _ => continue,
+         },
+         KeyCode::Backspace => {
+            model = larch::settle_with_effects::<ContextMod>(model, Some(Cmd::Save(name)))?;
+            Cmd::Last
+         }
+         KeyCode::Up => Cmd::Up,
+         KeyCode::Down => Cmd::Down,
+         KeyCode::Char(' ') => Cmd::Toggle(name.to_string(), model.cursor),
+         KeyCode::Char('s') => Cmd::Start(name.to_string(), model.cursor),
+         KeyCode::Char('p') => Cmd::Pause(name.to_string(), model.cursor),
+         KeyCode::Char('q') => {
+            larch::settle_with_effects::<ContextMod>(model, Some(Cmd::Save(name.to_string())))?;
+            return Ok(());
+         }
+         _ => continue,
+      };
+      model = larch::settle_with_effects::<ContextMod>(model, Some(msg))?;
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+
+   #[test]
+   fn render_marks_the_cursor_line() {
+      let view = View {
+         lines: vec!["## today".to_string(), " - [ ] write".to_string()],
+         cursor: 1,
+      };
+      assert_eq!(view.render(), "  ## today\n>  - [ ] write\n");
+   }
+}
@@ -0,0 +1,97 @@
+//! Every timeline save first copies what was on disk into a journal,
+//! so a change can be taken back with `inn undo`.
+use super::*;
+
+pub const IFNOTNOW_UNDO: &str = "undo";
+
+fn default_depth() -> usize {
+   20
+}
+
+/// A timeline as it was before a save.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UndoEntry {
+   pub name: String,
+   pub at: DTUtc,
+   pub yaml: String,
+}
+
+/// The journal keeps at most `depth` entries, dropping the oldest.
+/// Edit `depth` in the journal file to keep more or fewer. Unknown
+/// fields are refused, so a file that is not a journal is never read as
+/// an empty one and written over.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Journal {
+   #[serde(default = "default_depth")]
+   pub depth: usize,
+   #[serde(default)]
+   pub entries: Vec<UndoEntry>,
+}
+impl Journal {
+   fn filename() -> String {
+      ListV1::filename(IFNOTNOW_UNDO)
+   }
+   pub fn load() -> Result<Journal, INNError> {
+      match std::fs::read_to_string(Journal::filename()) {
+         Ok(text) => Ok(serde_yaml::from_str(&text)?),
+         Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Journal {
+            depth: default_depth(),
+            entries: vec![],
+         }),
+         Err(e) => Err(INNError::File(e)),
+      }
+   }
+   fn save(&self) -> Result<(), INNError> {
+      write_atomically(&Journal::filename(), &serde_yaml::to_string(self)?)
+   }
+   /// The entries for one timeline, oldest first.
+   pub fn entries_for<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a UndoEntry> {
+      self.entries.iter().filter(move |entry| entry.name == name)
+   }
+}
+
+/// Journals the file of the named timeline as it is now. A timeline
+/// with no file yet has nothing to take back.
+pub fn record(name: &str) -> Result<(), INNError> {
+   let yaml = match std::fs::read_to_string(ListV1::filename(name)) {
+      Ok(yaml) => yaml,
+      Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+      Err(e) => return Err(INNError::File(e)),
+   };
+   let _lock = lock::Lock::acquire(&Journal::filename())?;
+   let mut journal = Journal::load()?;
+   journal.entries.push(UndoEntry {
+      name: name.to_string(),
+      at: Utc::now(),
+      yaml,
+   });
+   let excess = journal.entries.len().saturating_sub(journal.depth);
+   journal.entries.drain(..excess);
+   journal.save()
+}
+
+/// Puts back the latest journaled state of the named timeline and drops
+/// it from the journal. The restore itself is not journaled.
+pub fn restore(name: &str) -> Result<UndoEntry, INNError> {
+   // The timeline before the journal, like a save.
+   let _timeline_lock = lock::Lock::acquire(&ListV1::filename(name))?;
+   let _lock = lock::Lock::acquire(&Journal::filename())?;
+   let mut journal = Journal::load()?;
+   let idx = match journal.entries.iter().rposition(|entry| entry.name == name) {
+      Some(idx) => idx,
+      None => return Err(INNError::NothingToUndo(name.to_string())),
+   };
+   let entry = journal.entries.remove(idx);
+   if dryrun::dry_run() {
+      dryrun::report(&ListV1 {
+         name: name.to_string(),
+         ..migrate::migrate(&entry.yaml, &ListV1::filename(name))?
+      })?;
+      return Ok(entry);
+   }
+   write_atomically(&ListV1::filename(name), &entry.yaml)?;
+   journal.save()?;
+   Ok(entry)
+}
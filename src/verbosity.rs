@@ -0,0 +1,50 @@
+//! How much `inn` says besides what a command is asked to show. Errors
+//! are always reported; `--quiet` drops notes and warnings, and
+//! `--verbose` adds traces of the files read and written and the items
+//! matched. The macros take `format!` arguments.
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+   Quiet,
+   Normal,
+   Verbose,
+}
+
+/// Set once from `--quiet` or `--verbose`.
+pub static VERBOSITY: OnceLock<Verbosity> = OnceLock::new();
+
+pub fn verbosity() -> Verbosity {
+   VERBOSITY.get().copied().unwrap_or(Verbosity::Normal)
+}
+
+macro_rules! error {
+   ($($arg:tt)*) => {
+      eprintln!("ERROR: {}", format!($($arg)*))
+   };
+}
+
+macro_rules! warn {
+   ($($arg:tt)*) => {
+      if $crate::verbosity::verbosity() >= $crate::verbosity::Verbosity::Normal {
+         eprintln!("WARNING: {}", format!($($arg)*))
+      }
+   };
+}
+
+/// What a command did, on stdout.
+macro_rules! info {
+   ($($arg:tt)*) => {
+      if $crate::verbosity::verbosity() >= $crate::verbosity::Verbosity::Normal {
+         println!($($arg)*)
+      }
+   };
+}
+
+macro_rules! debug {
+   ($($arg:tt)*) => {
+      if $crate::verbosity::verbosity() >= $crate::verbosity::Verbosity::Verbose {
+         eprintln!("debug: {}", format!($($arg)*))
+      }
+   };
+}
@@ -0,0 +1,58 @@
+//! Showing a timeline again each time its file changes, for
+//! `inn now --watch`.
+use super::*;
+use crossterm::{cursor, execute, terminal};
+use notify::{RecursiveMode, Watcher};
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// How long to wait for more events after one arrives. Editors and
+/// `write_atomically` touch a file several times for one save.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+impl From<notify::Error> for INNError {
+   fn from(e: notify::Error) -> INNError {
+      INNError::File(std::io::Error::other(e))
+   }
+}
+
+/// Clears the screen and calls `show` once, then again after every
+/// change to `filename`, until interrupted. Errors from `show` are
+/// printed and watching goes on, so a file broken by an edit shows up
+/// again once it is fixed.
+pub fn watch(filename: &str, show: &dyn Fn() -> Result<(), INNError>) -> Result<(), INNError> {
+   let path = match std::fs::canonicalize(filename) {
+      Ok(path) => path,
+      Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+         return Err(INNError::NotFound(filename.to_string()))
+      }
+      Err(e) => return Err(INNError::File(e)),
+   };
+   // Saves replace the file, so the directory is watched rather than
+   // the file itself.
+   let dir = path.parent().unwrap_or_else(|| Path::new("."));
+   let (tx, rx) = mpsc::channel();
+   let mut watcher = notify::recommended_watcher(tx)?;
+   watcher.watch(dir, RecursiveMode::NonRecursive)?;
+   loop {
+      execute!(
+         std::io::stdout(),
+         terminal::Clear(terminal::ClearType::All),
+         cursor::MoveTo(0, 0)
+      )?;
+      if let Err(e) = show() {
+         error!("{}", e);
+      }
+      // Wait for an event about the file, then for things to settle.
+      loop {
+         let event: notify::Event = match rx.recv() {
+            Ok(event) => event?,
+            Err(_) => return Ok(()),
+         };
+         if event.paths.iter().any(|p| p == &path) {
+            break;
+         }
+      }
+      while rx.recv_timeout(DEBOUNCE).is_ok() {}
+   }
+}
@@ -0,0 +1,177 @@
+//! Runs the `ifnotnow` binary against timelines in a directory of its
+//! own, one per test.
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+struct Store {
+   dir: PathBuf,
+}
+impl Store {
+   fn new(test: &str) -> Store {
+      let dir = std::env::temp_dir().join(format!("ifnotnow-{}-{}", test, std::process::id()));
+      let _ = std::fs::remove_dir_all(&dir);
+      std::fs::create_dir_all(&dir).unwrap();
+      Store { dir }
+   }
+   fn inn(&self, args: &[&str]) -> Output {
+      Command::new(env!("CARGO_BIN_EXE_ifnotnow"))
+         .arg("--dir")
+         .arg(&self.dir)
+         .arg("--color")
+         .arg("never")
+         .args(args)
+         .env("INN_CONFIG", self.dir.join("config.yaml"))
+         .output()
+         .unwrap()
+   }
+   fn read(&self, name: &str) -> String {
+      std::fs::read_to_string(self.dir.join(format!("{}.inn.yaml", name))).unwrap()
+   }
+}
+impl Drop for Store {
+   fn drop(&mut self) {
+      let _ = std::fs::remove_dir_all(&self.dir);
+   }
+}
+
+fn stderr(output: &Output) -> String {
+   String::from_utf8_lossy(&output.stderr).to_string()
+}
+
+#[test]
+fn done_refuses_an_ambiguous_label() {
+   let store = Store::new("done-ambiguous");
+   assert!(store.inn(&["init", "starter"]).status.success());
+   let before = store.read("starter");
+   let output = store.inn(&["done", "starter", "--item", "A TODO Item"]);
+   assert!(!output.status.success());
+   assert!(stderr(&output).contains("3: A TODO Item, 5: A TODO Item"));
+   assert_eq!(store.read("starter"), before);
+}
+
+#[test]
+fn remove_refuses_an_ambiguous_label() {
+   let store = Store::new("remove-ambiguous");
+   assert!(store.inn(&["init", "starter"]).status.success());
+   let before = store.read("starter");
+   let output = store.inn(&["remove", "starter", "--item", "A TODO Item"]);
+   assert!(!output.status.success());
+   assert!(stderr(&output).contains("3: A TODO Item, 5: A TODO Item"));
+   assert_eq!(store.read("starter"), before);
+   // A selector naming one item still removes it.
+   assert!(store.inn(&["remove", "starter", "--item", "5"]).status.success());
+   assert_eq!(store.read("starter").matches("A TODO Item").count(), 1);
+}
+
+#[test]
+fn add_and_now_reach_their_handlers() {
+   let store = Store::new("add-now");
+   assert!(store.inn(&["init", "today"]).status.success());
+   let output = store.inn(&["add", "today", "--goal", "write", "--note", "first draft"]);
+   assert!(output.status.success(), "{}", stderr(&output));
+   let output = store.inn(&["now", "today"]);
+   assert!(output.status.success(), "{}", stderr(&output));
+   let shown = String::from_utf8_lossy(&output.stdout).to_string();
+   assert_eq!(shown, "# today\n - [ ] write\n> first draft\n\n");
+   // `now` remembers the timeline, so it can be left out next time.
+   assert_eq!(store.inn(&["now"]).stdout, output.stdout);
+}
+
+#[test]
+fn add_creates_a_missing_timeline() {
+   let store = Store::new("add-creates");
+   let output = store.inn(&["add", "inbox", "--heading", "Errands", "--timebox", "shop"]);
+   assert!(output.status.success(), "{}", stderr(&output));
+   let yaml = store.read("inbox");
+   assert!(yaml.contains("name: inbox"));
+   assert!(yaml.find("Heading: Errands").unwrap() < yaml.find("label: shop").unwrap());
+}
+
+#[test]
+fn bookkeeping_names_are_refused() {
+   let store = Store::new("reserved");
+   assert!(store.inn(&["init", "a"]).status.success());
+   assert!(store.inn(&["now", "a"]).status.success());
+   for args in [
+      &["init", "model"][..],
+      &["add", "model", "--goal", "x"],
+      &["rename", "a", "model"],
+      &["clone", "a", "model"],
+      &["import", "todo.txt", "model", "--todotxt"],
+      &["init", "model", "--from-stdin"],
+   ] {
+      let output = store.inn(args);
+      assert!(!output.status.success(), "{:?}", args);
+      assert!(stderr(&output).contains("\"model\" is the name of a file inn keeps"), "{:?}", args);
+   }
+   // The model is intact, so the store still works.
+   assert!(store.read("model").contains("now_context: a"));
+   assert!(store.inn(&["init", "b"]).status.success());
+}
+
+#[test]
+fn undo_journal_is_not_a_timeline() {
+   let store = Store::new("reserved-undo");
+   assert!(store.inn(&["init", "a"]).status.success());
+   let output = store.inn(&["add", "undo", "--goal", "x"]);
+   assert!(!output.status.success());
+   assert!(stderr(&output).contains("\"undo\" is the name of a file inn keeps"));
+   // A timeline where the journal should be is refused, not written over.
+   let timeline = store.read("a");
+   std::fs::write(store.dir.join("undo.inn.yaml"), &timeline).unwrap();
+   let output = store.inn(&["add", "a", "--goal", "y"]);
+   assert!(!output.status.success());
+   assert!(stderr(&output).contains("unknown field"), "{}", stderr(&output));
+   assert_eq!(store.read("undo"), timeline);
+}
+
+#[test]
+fn rename_to_the_same_name_does_nothing() {
+   let store = Store::new("rename-same");
+   assert!(store.inn(&["init", "a"]).status.success());
+   let before = store.read("a");
+   let start = std::time::Instant::now();
+   let output = store.inn(&["rename", "a", "a"]);
+   assert!(output.status.success(), "{}", stderr(&output));
+   assert!(start.elapsed() < std::time::Duration::from_secs(1));
+   assert!(!stderr(&output).contains("held by another inn"));
+   assert_eq!(store.read("a"), before);
+   let output = store.inn(&["rename", "b", "b"]);
+   assert!(stderr(&output).contains("b.inn.yaml not found"));
+}
+
+#[test]
+fn list_skips_a_malformed_timeline() {
+   let store = Store::new("list-malformed");
+   assert!(store.inn(&["init", "a"]).status.success());
+   assert!(store.inn(&["init", "c"]).status.success());
+   std::fs::write(store.dir.join("b.inn.yaml"), "name: b\nitems: [\n").unwrap();
+   let output = store.inn(&["list"]);
+   assert!(output.status.success(), "{}", stderr(&output));
+   let listed = String::from_utf8_lossy(&output.stdout).to_string();
+   let names: Vec<&str> = listed.lines().map(|line| line.split('\t').next().unwrap()).collect();
+   assert_eq!(names, ["a", "c"]);
+   assert!(stderr(&output).contains("skipping"));
+   assert!(stderr(&output).contains("b.inn.yaml"));
+}
+
+#[test]
+fn quiet_dry_run_says_nothing() {
+   let store = Store::new("quiet-dry-run");
+   assert!(store.inn(&["init", "starter"]).status.success());
+   let out = store.dir.join("out.todo");
+   let out = out.to_str().unwrap();
+   for args in [
+      &["rename", "starter", "other"][..],
+      &["edit", "starter"],
+      &["export", "starter", out, "--todotxt"],
+      &["view"],
+   ] {
+      let output = store.inn(&[&["-q", "--dry-run"][..], args].concat());
+      assert!(output.status.success(), "{:?}: {}", args, stderr(&output));
+      assert!(output.stdout.is_empty() && output.stderr.is_empty(), "{:?}", args);
+   }
+   let output = store.inn(&["--dry-run", "rename", "starter", "other"]);
+   assert!(String::from_utf8_lossy(&output.stdout).contains("dry run: not renaming"));
+   assert!(!store.dir.join("other.inn.yaml").exists());
+}